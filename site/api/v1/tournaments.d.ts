@@ -0,0 +1,192 @@
+// @generated by ssg/src/generate_types.rs from tournaments.schema.json. Do not edit by hand.
+
+/** Public listing of upcoming Super Smash Bros. Melee majors, as served from https://meleemajors.gg/api/v1/tournaments.json. Regenerated on every site build. */
+export interface TournamentsResponse {
+  /** Reference to the JSON Schema this payload conforms to, resolved relative to the payload's own URL. Present so editors and validators can auto-discover the schema without hard-coding a host. */
+  $schema?: string;
+  /** RFC 3339 timestamp of the build in which tournament data last changed. Stays the same across builds that scrape identical data, so it is safe to use for caching and change detection. */
+  lastUpdated: string;
+  /** RFC 3339 timestamp of when this payload was generated. Updates on every site build, even when tournament data is unchanged. */
+  generatedAt: string;
+  /** All tracked tournaments, ordered chronologically by start date (soonest first). */
+  tournaments: Tournament[];
+}
+
+/** Rolling history of what changed between site builds, served from https://meleemajors.gg/api/v1/changes.json. A build only adds an entry when tournament data actually changed. */
+export interface ChangeLog {
+  /** Same value as `lastUpdated` in tournaments.json: when tournament data last changed. */
+  lastUpdated: string;
+  /** Entries older than this many days are dropped from the log. */
+  retentionDays: number;
+  /** One entry per build that changed tournament data, newest first. */
+  entries: ChangeLogEntry[];
+}
+
+export interface ChangeLogEntry {
+  /** RFC 3339 timestamp of the build that detected these changes. */
+  timestamp: string;
+  /** Tournaments that appeared since the previous build. */
+  added: TournamentSummary[];
+  /** Tournaments that disappeared since the previous build, either because they ended or were dropped from the listing. */
+  removed: TournamentSummary[];
+  /** Tournaments present in both builds whose data differs. */
+  changed: TournamentChange[];
+}
+
+export interface TournamentSummary {
+  /** Same value as the tournament's `slug`. */
+  slug: string;
+  /** Same value as the tournament's `name`. */
+  name: string;
+}
+
+export interface TournamentChange {
+  /** Same value as the tournament's `slug`. */
+  slug: string;
+  /** The tournament's `name` as of this build. */
+  name: string;
+  /** Each top-level tournament field whose value changed. Deprecated aliases are omitted. */
+  fields: FieldChange[];
+}
+
+export interface FieldChange {
+  /** Name of the changed tournament field. */
+  field: string;
+  /** Value in the previous build. */
+  from: unknown;
+  /** Value in this build. */
+  to: unknown;
+}
+
+/** Body of every outbound webhook request. `tournament` is as of this build, or as of the previous build for `removed` and `ended` events. */
+export interface WebhookEvent {
+  /** Unique per event and endpoint, and the same across retries, so duplicate deliveries can be ignored. */
+  id: string;
+  /** What happened to the tournament. `removed` means it was taken off the schedule before it ended. */
+  event: "added" | "changed" | "removed" | "started" | "ended";
+  /** When the build that found the event ran. */
+  timestamp: string;
+  tournament: Tournament;
+  /** What changed, for `changed` events. */
+  changes?: FieldChange[];
+}
+
+/** Lightweight listing served from https://meleemajors.gg/api/v1/index.json. Poll this and compare each entry's hash to decide which per-tournament resources to re-fetch. */
+export interface Index {
+  /** Same value as `lastUpdated` in tournaments.json: when tournament data last changed. */
+  lastUpdated: string;
+  /** One entry per tracked tournament, ordered chronologically by start date (soonest first). */
+  tournaments: IndexEntry[];
+}
+
+export interface IndexEntry {
+  /** Same value as the tournament's `slug`. */
+  slug: string;
+  /** Absolute URL of the per-tournament resource, which conforms to #/$defs/tournament. */
+  url: string;
+  /** RFC 3339 timestamp of the build in which this tournament's data last changed. */
+  lastModified: string;
+  /** SHA-256 of the resource's compact JSON serialization. Changes whenever any field of the tournament changes. */
+  hash: string;
+}
+
+/** Per-player index served from https://meleemajors.gg/api/v1/players.json, listing which upcoming majors each ranked player is registered for. */
+export interface Players {
+  /** Same value as `lastUpdated` in tournaments.json: when tournament data last changed. */
+  lastUpdated: string;
+  /** Every tracked player, ordered by rank. Players who aren't registered for any upcoming major have an empty `tournaments` list. */
+  players: Player[];
+}
+
+/** A ranked player and the upcoming majors they're registered for. Also served individually from https://meleemajors.gg/api/v1/players/<slug>.json. */
+export interface Player {
+  /** Player tag as it appears in tournaments' `players` lists. */
+  name: string;
+  /** URL-safe identifier derived from the tag. Stable across builds. */
+  slug: string;
+  /** Position in meleemajors.gg's tracked player list: the latest SSBMRank top 50, followed by notable players who have dropped out of it. */
+  rank: number;
+  /** Absolute URL of this player's individual resource. */
+  url: string;
+  /** Absolute URL of an iCalendar feed containing only the majors this player is registered for. */
+  calendarUrl: string;
+  /** Upcoming majors the player is registered for, ordered chronologically by start date (soonest first). */
+  tournaments: PlayerAppearance[];
+}
+
+export interface PlayerAppearance {
+  /** Same value as the tournament's `slug`. */
+  slug: string;
+  /** Same value as the tournament's `name`. */
+  name: string;
+  /** Same value as the tournament's `startTimestamp`. */
+  startTimestamp: string;
+  /** Same value as the tournament's `endTimestamp`. */
+  endTimestamp: string;
+  /** Same value as the tournament's `dateString`. */
+  dateString: string;
+  /** Same value as the tournament's `cityAndState`. */
+  cityAndState: string;
+  /** Same value as the tournament's `bracketUrl`. */
+  bracketUrl: string;
+  /** Absolute URL of the tournament's individual resource. */
+  url: string;
+}
+
+export interface Tournament {
+  /** Human-readable tournament name as displayed on the site (e.g. "Riptide 2026", "Pat's House 5"). */
+  name: string;
+  /** Internal slug-like identifier derived from the tournament's URL. Stable across builds and safe to use as a key. */
+  slug: string;
+  /** RFC 3339 timestamp for when the tournament begins (typically the first day's check-in or first match). UTC-offset is the event's local offset. */
+  startTimestamp: string;
+  /** RFC 3339 timestamp for when the tournament ends, as reported by start.gg. Reflected verbatim without any end-of-day rounding. */
+  endTimestamp: string;
+  /** Pre-formatted human-readable date range intended for display (e.g. "April 10 - April 12"). Format is not machine-parseable; use startTimestamp for programmatic date handling. */
+  dateString: string;
+  /** IANA timezone name for the tournament venue. Pair with startTimestamp to render local times. */
+  timezone: string;
+  /** Announced start time of Top 8, in the organizer's preferred format. Free-form; null if not yet scheduled. */
+  top8StartTime: string | null;
+  /** Total registered entrants in the main singles bracket. null if registration has not opened or the count is otherwise unknown. */
+  entrants: number | null;
+  /** Fixed-length list of the top 8 seeded/notable entrants, ordered by seed. Slots that have not yet been announced are null. */
+  players: (string | null)[];
+  /** Short locality string for display (city + state/region). Free-form; format varies by country. */
+  cityAndState: string;
+  /** Full postal address of the venue, as provided by the organizer. Free-form. */
+  fullAddress: string;
+  /** Google Maps search URL for the venue address. Derived from fullAddress. */
+  mapsLink: string;
+  /** Canonical start.gg URL for the main Melee singles event page. */
+  bracketUrl: string;
+  /** start.gg URL for the tournament's overview /details page, which lists all events and general info. Prefer this over bracketUrl for general-purpose linking. null if the details URL cannot be derived from bracketUrl. */
+  tournamentUrl: string | null;
+  /** Primary livestream URL (typically Twitch or YouTube). null if no stream has been announced. */
+  streamUrl: string | null;
+  /** URL to the published event schedule. null if unavailable. */
+  scheduleUrl: string | null;
+  /** Absolute URL to the tournament's promotional banner image (wide aspect), self-hosted on meleemajors.gg. */
+  imageUrl: string;
+  /** Absolute URL to the tournament's square thumbnail/profile image (typically used as an icon), self-hosted on meleemajors.gg. null if start.gg does not expose a distinct profile image for this tournament. */
+  thumbnailUrl: string | null;
+  /** Most common color in the banner image as a lowercase hex color. Useful as a loading placeholder or accent color matching the event branding. */
+  imageColor: string;
+  /** Tiny blurred version of the banner image as a base64 WebP data: URI, for showing while imageUrl loads. */
+  imagePlaceholder: string;
+  /**
+   * DEPRECATED: use `slug` instead. Retained for backwards compatibility and emits the same value. Internal slug-like identifier derived from the tournament's start.gg URL.
+   * @deprecated
+   */
+  startggTournamentName: string;
+  /**
+   * DEPRECATED: use `bracketUrl` instead. Retained for backwards compatibility and emits the same value. Canonical start.gg URL for the main Melee singles event page.
+   * @deprecated
+   */
+  startggUrl: string;
+  /**
+   * DEPRECATED: use `tournamentUrl` instead. Retained for backwards compatibility and emits the same value. start.gg URL for the tournament's overview /details page. null if the details URL cannot be derived from bracketUrl.
+   * @deprecated
+   */
+  startggDetailsUrl: string | null;
+}
@@ -103,6 +103,24 @@ function emailButton(event) {
   }, isCalendarOpen ? 300 : 0)
 }
 
+// keep the signup form's region / player checkboxes consistent with "every major":
+// picking anything specific unchecks it, and unchecking everything rechecks it
+function emailPreferenceChanged(event) {
+  const checkboxes = document.querySelectorAll(".email-preferences input")
+  const everything = document.querySelector(".email-preferences input[data-everything]")
+  if (event.currentTarget === everything && everything.checked) {
+    for (const checkbox of checkboxes) {
+      if (checkbox !== everything) checkbox.checked = false
+    }
+  }
+  else if (event.currentTarget.checked) {
+    everything.checked = false
+  }
+  if (![...checkboxes].some((checkbox) => checkbox.checked)) {
+    everything.checked = true
+  }
+}
+
 function scrollToBottom() {
   const startTime = Date.now();
   const animationDuration = 400;
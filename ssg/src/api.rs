@@ -0,0 +1,343 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::changes;
+use crate::model::{Tournament, TournamentsResponse};
+use crate::paths::Paths;
+use crate::utils::{absolute_url, log_error, log_red, log_skip, log_success, log_warn};
+
+/// Writes `/api/v1/tournaments.json`, a resource per tournament, the index and
+/// the change log since the previous build, all validated against the schema.
+/// Returns the `/api/v1/tournaments.json` payload.
+pub fn make_api(tournaments: &[Value], last_updated: &DateTime<Utc>, paths: &Paths) -> Value {
+    let payload = serde_json::to_value(api_payload(tournaments, last_updated)).unwrap();
+    let api_tournaments = payload["tournaments"].as_array().unwrap();
+
+    let schema = read_api_schema(paths);
+    validate_api_payload(&schema, "#", &payload);
+
+    // Read the previous build's output before overwriting it, to diff against
+    let previous_payload = changes::read_previous_payload(paths);
+
+    let out_path = paths.site("api/v1/tournaments.json");
+    fs::create_dir_all(std::path::Path::new(&out_path).parent().unwrap()).unwrap();
+    fs::write(&out_path, serde_json::to_string_pretty(&payload).unwrap()).unwrap();
+    log_success("api", "wrote /api/v1/tournaments.json");
+
+    make_api_resources(&schema, api_tournaments, last_updated, paths);
+
+    // The change log is always written, so the endpoint exists from the first build
+    let diff = match previous_payload {
+        Some(previous_payload) => changes::diff_payloads(&previous_payload, &payload),
+        None => {
+            log_warn(
+                "changes",
+                "no previous /api/v1/tournaments.json to diff against; not adding an entry",
+            );
+            changes::TournamentDiff::default()
+        }
+    };
+    changes::make_change_log(&schema, &diff, last_updated, paths);
+    payload
+}
+
+/// The `/api/v1/tournaments.json` payload for the given scraped tournament data.
+pub fn api_payload(tournaments: &[Value], last_updated: &DateTime<Utc>) -> TournamentsResponse {
+    let mut sorted = tournaments.to_vec();
+    sorted.sort_by_key(|t| t["start-unix-timestamp"].as_i64().unwrap_or(i64::MAX));
+    TournamentsResponse {
+        schema: Some("tournaments.schema.json".to_string()),
+        last_updated: last_updated.to_rfc3339(),
+        generated_at: Utc::now().to_rfc3339(),
+        tournaments: sorted.iter().map(tournament_to_api).collect(),
+    }
+}
+
+/// When the tournament data last changed: the previous build's `lastUpdated` if
+/// the API tournaments hash the same as the previously published ones, otherwise now.
+pub fn data_last_updated(tournaments: &[Value], paths: &Paths) -> DateTime<Utc> {
+    let now = Utc::now();
+    let Some(previous_payload) = changes::read_previous_payload(paths) else {
+        return now;
+    };
+    let previous_last_updated = previous_payload["lastUpdated"]
+        .as_str()
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+        .map(|timestamp| timestamp.with_timezone(&Utc));
+
+    let current_tournaments = serde_json::to_value(api_payload(tournaments, &now).tournaments);
+    let unchanged = current_tournaments.is_ok_and(|current| {
+        content_hash(&previous_payload["tournaments"]) == content_hash(&current)
+    });
+
+    match previous_last_updated {
+        Some(previous_last_updated) if unchanged => {
+            log_skip(
+                "data",
+                &format!("unchanged since {}", previous_last_updated.to_rfc3339()),
+            );
+            previous_last_updated
+        }
+        _ => now,
+    }
+}
+
+/// Writes one `/api/v1/tournaments/<slug>.json` per tournament, plus an
+/// `/api/v1/index.json` listing each slug with a content hash and the time that
+/// hash last changed, so clients can poll the index instead of every resource.
+fn make_api_resources(
+    schema: &Value,
+    api_tournaments: &[Value],
+    last_updated: &DateTime<Utc>,
+    paths: &Paths,
+) {
+    let resources_path = paths.site("api/v1/tournaments");
+    fs::create_dir_all(&resources_path).unwrap();
+
+    // Carry `lastModified` over from the previous build for unchanged tournaments
+    let index_path = paths.site("api/v1/index.json");
+    let previous_index: HashMap<String, (String, String)> = fs::read_to_string(&index_path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        .and_then(|index| index["tournaments"].as_array().cloned())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|entry| {
+            Some((
+                entry["slug"].as_str()?.to_string(),
+                (
+                    entry["hash"].as_str()?.to_string(),
+                    entry["lastModified"].as_str()?.to_string(),
+                ),
+            ))
+        })
+        .collect();
+
+    let now = last_updated.to_rfc3339();
+    let mut index_entries: Vec<Value> = Vec::new();
+    let mut resource_files: HashSet<String> = HashSet::new();
+    for tournament in api_tournaments {
+        validate_api_payload(schema, "#/$defs/tournament", tournament);
+
+        let slug = tournament["slug"].as_str().unwrap();
+        let hash = content_hash(tournament);
+        let last_modified = match previous_index.get(slug) {
+            Some((previous_hash, previous_modified)) if *previous_hash == hash => {
+                previous_modified.clone()
+            }
+            _ => now.clone(),
+        };
+
+        let file_name = format!("{slug}.json");
+        fs::write(
+            format!("{resources_path}/{file_name}"),
+            serde_json::to_string_pretty(tournament).unwrap(),
+        )
+        .unwrap();
+        resource_files.insert(file_name);
+
+        index_entries.push(json!({
+            "slug": slug,
+            "url": format!("https://meleemajors.gg/api/v1/tournaments/{slug}.json"),
+            "lastModified": last_modified,
+            "hash": hash,
+        }));
+    }
+
+    // Remove resources for tournaments that have ended or been dropped
+    for entry in fs::read_dir(&resources_path).unwrap() {
+        let path = entry.unwrap().path();
+        let file_name = path.file_name().unwrap().to_str().unwrap().to_string();
+        if !resource_files.contains(&file_name) {
+            fs::remove_file(path).ok();
+        }
+    }
+    log_success(
+        "api",
+        &format!(
+            "wrote {} /api/v1/tournaments/<slug>.json",
+            resource_files.len()
+        ),
+    );
+
+    let index = json!({
+        "lastUpdated": now,
+        "tournaments": index_entries,
+    });
+    validate_api_payload(schema, "#/$defs/index", &index);
+    fs::write(&index_path, serde_json::to_string_pretty(&index).unwrap()).unwrap();
+    log_success("api", "wrote /api/v1/index.json");
+}
+
+/// `sha256:`-prefixed hex digest of the compact JSON serialization of `value`.
+fn content_hash(value: &Value) -> String {
+    let digest = Sha256::digest(serde_json::to_string(value).unwrap().as_bytes());
+    format!("sha256:{}", hex::encode(digest))
+}
+
+/// tournaments.schema.json from the static directory.
+pub fn read_api_schema(paths: &Paths) -> Value {
+    let schema_path = paths.static_file("api/v1/tournaments.schema.json");
+    serde_json::from_str(&fs::read_to_string(&schema_path).unwrap())
+        .expect("tournaments.schema.json is not valid JSON")
+}
+
+/// Validates `payload` against the part of tournaments.schema.json at
+/// `schema_ref`, e.g. `"#"` for the whole payload or `"#/$defs/tournament"`.
+pub fn validate_api_payload(schema: &Value, schema_ref: &str, payload: &Value) {
+    let schema = if schema_ref == "#" {
+        schema.clone()
+    } else {
+        json!({
+            "$schema": schema["$schema"],
+            "$id": schema["$id"],
+            "$ref": schema_ref,
+            "$defs": schema["$defs"],
+        })
+    };
+    let validator = jsonschema::validator_for(&schema)
+        .expect("tournaments.schema.json is not a valid JSON Schema");
+
+    let errors: Vec<String> = validator
+        .iter_errors(payload)
+        .map(|e| format!("  at {}: {}", e.instance_path, e))
+        .collect();
+
+    if errors.is_empty() {
+        if schema_ref == "#" {
+            log_success("api", "validated against tournaments.schema.json");
+        }
+    } else {
+        log_error(
+            "api",
+            &format!("payload failed schema validation against {schema_ref}:"),
+        );
+        for err in &errors {
+            log_red(err);
+        }
+        panic!("api payload does not match tournaments.schema.json{schema_ref}");
+    }
+}
+
+/// A scraped tournament as it appears in the API.
+#[allow(deprecated)]
+pub fn tournament_to_api(t: &Value) -> Tournament {
+    let string = |key: &str| t[key].as_str().unwrap_or_default().to_string();
+    let players = (0..8)
+        .map(|i| t[format!("player{i}")].as_str().map(str::to_string))
+        .collect();
+
+    let start_timestamp = t["start-unix-timestamp"]
+        .as_i64()
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default();
+
+    let end_timestamp = t["end-unix-timestamp"]
+        .as_i64()
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default();
+
+    let image_url = format!(
+        "https://meleemajors.gg{}",
+        t["image-url"].as_str().unwrap_or("")
+    );
+
+    let thumbnail_url = t["image-url-thumbnail"]
+        .as_str()
+        .map(|path| format!("https://meleemajors.gg{path}"));
+
+    let entrants = t["entrants"].as_str().and_then(|s| s.parse::<u64>().ok());
+
+    let non_empty = |key: &str| -> Option<String> {
+        t[key]
+            .as_str()
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+
+    let startgg_url = t["start.gg-url"].as_str().unwrap_or("");
+    let startgg_details_url = startgg_url
+        .split_once("/event/")
+        .map(|(base, _)| format!("{base}/details"));
+
+    Tournament {
+        name: string("name"),
+        slug: string("start.gg-tournament-name"),
+        start_timestamp,
+        end_timestamp,
+        date_string: string("date"),
+        timezone: string("timezone"),
+        top8_start_time: non_empty("top8-start-time"),
+        entrants,
+        players,
+        city_and_state: string("city-and-state"),
+        full_address: string("full-address"),
+        maps_link: string("maps-link"),
+        bracket_url: startgg_url.to_string(),
+        tournament_url: startgg_details_url.clone(),
+        stream_url: non_empty("stream-url"),
+        schedule_url: non_empty("schedule-url").as_deref().map(absolute_url),
+        image_url,
+        thumbnail_url,
+        image_color: string("image-color"),
+        image_placeholder: string("image-placeholder"),
+        // Deprecated: superseded by `slug`. Kept for backwards compatibility.
+        startgg_tournament_name: string("start.gg-tournament-name"),
+        // Deprecated: superseded by `bracketUrl`. Kept for backwards compatibility.
+        startgg_url: startgg_url.to_string(),
+        // Deprecated: superseded by `tournamentUrl`. Kept for backwards compatibility.
+        startgg_details_url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tournament(entrants: &str) -> Value {
+        json!({
+            "start.gg-tournament-name": "sapf2",
+            "name": "SAPF 2",
+            "start-unix-timestamp": 1775826000,
+            "end-unix-timestamp": 1776034800,
+            "entrants": entrants,
+        })
+    }
+
+    #[test]
+    fn last_updated_only_changes_with_the_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::for_test(dir.path());
+        let previous_last_updated = DateTime::parse_from_rfc3339("2026-10-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        fs::create_dir_all(paths.site("api/v1")).unwrap();
+        fs::write(
+            paths.site("api/v1/tournaments.json"),
+            serde_json::to_string(&api_payload(&[tournament("245")], &previous_last_updated))
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            data_last_updated(&[tournament("245")], &paths),
+            previous_last_updated
+        );
+
+        let before = Utc::now();
+        assert!(data_last_updated(&[tournament("300")], &paths) >= before);
+    }
+
+    #[test]
+    fn first_build_is_last_updated_now() {
+        let dir = tempfile::tempdir().unwrap();
+        let before = Utc::now();
+        assert!(data_last_updated(&[tournament("245")], &Paths::for_test(dir.path())) >= before);
+    }
+}
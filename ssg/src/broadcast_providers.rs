@@ -0,0 +1,559 @@
+use std::fs;
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use reqwest::{
+    header::{self, HeaderMap, HeaderValue},
+    Client,
+};
+use serde_json::{json, to_string_pretty, Value};
+
+use crate::paths::Paths;
+use crate::utils::{log_red, log_yellow, slug};
+
+/// Nothing the ssg schedules is sent more than this long after it's created,
+/// so older v3 broadcasts are assumed sent without looking them up.
+const V3_SCHEDULE_HORIZON_DAYS: i64 = 365;
+
+/// A broadcast as stored by a provider. Only the fields we read back are kept.
+pub struct Broadcast {
+    /// Provider-specific ID, used to update or delete the broadcast
+    pub id: String,
+    pub subject: String,
+    /// `None` for drafts and broadcasts the provider didn't report a time for
+    pub send_at: Option<DateTime<Utc>>,
+    /// Internal note that subscribers don't see
+    pub description: Option<String>,
+}
+
+/// A broadcast to create, or to replace an existing one with.
+pub struct BroadcastDraft {
+    pub subject: String,
+    /// HTML
+    pub content: String,
+    /// Plain text alternative to `content`. Kit's API has no field for one (it
+    /// always generates its own from the HTML), so only the local provider
+    /// uses this
+    pub text_content: String,
+    pub send_at: DateTime<Utc>,
+    /// Internal note that subscribers don't see
+    pub description: String,
+    /// Only subscribers with at least one of these tags get the broadcast.
+    /// Empty sends it to everyone
+    pub tag_ids: Vec<u64>,
+}
+
+/// The operations `MailingListService` needs from an email provider.
+#[async_trait]
+pub trait BroadcastProvider: Send + Sync {
+    /// Shown in logs, e.g. "Kit v4"
+    fn name(&self) -> &'static str;
+
+    /// Schedules a broadcast to `draft.tag_ids` (or every subscriber) at `draft.send_at`.
+    async fn create_broadcast(&self, draft: &BroadcastDraft) -> Result<Broadcast>;
+
+    /// Replaces everything in a scheduled broadcast with `draft`.
+    async fn update_broadcast(&self, id: &str, draft: &BroadcastDraft) -> Result<()>;
+
+    /// Broadcasts that are scheduled but haven't been sent yet, and whose
+    /// subject starts with one of `subject_prefixes`. Providers that need a
+    /// request per broadcast use the prefixes to skip the rest.
+    async fn list_scheduled_broadcasts(&self, subject_prefixes: &[&str]) -> Result<Vec<Broadcast>>;
+
+    async fn delete_broadcast(&self, id: &str) -> Result<()>;
+}
+
+/// Picks a provider from the environment.
+///
+/// `EMAIL_PROVIDER` can be `kit-v4`, `kit-v3` or `local`. When it isn't set,
+/// Kit v4 is used if `KIT_V4_API_TOKEN` is set, and Kit v3 otherwise.
+pub fn provider_from_env(paths: &Paths) -> Result<Box<dyn BroadcastProvider>> {
+    let provider = env_var("EMAIL_PROVIDER").unwrap_or_else(|| {
+        if env_var("KIT_V4_API_TOKEN").is_some() {
+            "kit-v4".to_string()
+        } else {
+            "kit-v3".to_string()
+        }
+    });
+    match provider.as_str() {
+        "kit-v4" => Ok(Box::new(KitV4Provider::new(&required_env_var(
+            "KIT_V4_API_TOKEN",
+        )?)?)),
+        "kit-v3" => Ok(Box::new(KitV3Provider::new(&required_env_var(
+            "KIT_V3_API_SECRET",
+        )?)?)),
+        "local" => {
+            let dir = env_var("EMAIL_OUTBOX_DIR").unwrap_or_else(|| paths.preview("outbox"));
+            Ok(Box::new(LocalProvider::new(&dir)?))
+        }
+        other => bail!("unknown EMAIL_PROVIDER {other:?}, expected kit-v4, kit-v3 or local"),
+    }
+}
+
+/// Treats empty values (e.g. `KEY=` left blank in .env) as unset
+fn env_var(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+fn required_env_var(key: &str) -> Result<String> {
+    env_var(key).ok_or_else(|| {
+        log_red("Missing API credentials for Kit");
+        log_yellow("Generate them here:");
+        log_yellow("https://app.kit.com/account_settings/developer_settings");
+        log_yellow("Then add them to .env or run.sh");
+        log_yellow(&format!("{key}=your-api-key"));
+        anyhow!("missing {key}")
+    })
+}
+
+fn has_prefix(subject: &str, subject_prefixes: &[&str]) -> bool {
+    subject_prefixes
+        .iter()
+        .any(|prefix| subject.starts_with(prefix))
+}
+
+fn parse_send_at(value: &Value) -> Option<DateTime<Utc>> {
+    value
+        .as_str()
+        .and_then(|send_at| DateTime::parse_from_rfc3339(send_at).ok())
+        .map(|send_at| send_at.with_timezone(&Utc))
+}
+
+/// Logs and returns an error for a failed API response, keeping the body for context.
+async fn error_response(response: reqwest::Response) -> anyhow::Error {
+    let status = response.status();
+    let body = response.json::<Value>().await.unwrap_or(Value::Null);
+    let response_code_str = format!("Response code {}", status.as_str());
+    log_red(&response_code_str);
+    log_red(&to_string_pretty(&body).unwrap_or_default());
+    anyhow!(response_code_str).context(body)
+}
+
+/// Kit API v4, authenticated with a bearer token.
+/// https://developers.kit.com/api-reference/broadcasts/list-broadcasts
+pub struct KitV4Provider {
+    client: Client,
+}
+
+impl KitV4Provider {
+    const BASE_URL: &'static str = "https://api.kit.com/v4/broadcasts";
+
+    pub fn new(api_token: &str) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {api_token}"))?,
+        );
+        let client = Client::builder().default_headers(headers).build()?;
+        Ok(Self { client })
+    }
+
+    fn broadcast(json: &Value) -> Result<Broadcast> {
+        Ok(Broadcast {
+            id: json["id"]
+                .as_i64()
+                .context("missing broadcast id")?
+                .to_string(),
+            subject: json["subject"]
+                .as_str()
+                .unwrap_or("(no subject)")
+                .to_string(),
+            send_at: parse_send_at(&json["send_at"]),
+            description: json["description"].as_str().map(String::from),
+        })
+    }
+
+    fn body(draft: &BroadcastDraft) -> Value {
+        let mut body = json!({
+            "email_template_id": Value::Null, // use default template
+            "content": draft.content,
+            "description": draft.description,
+            "subject": draft.subject,
+            "send_at": draft.send_at.to_rfc3339(),
+            "public": true, // false == draft
+        });
+        if !draft.tag_ids.is_empty() {
+            body["subscriber_filter"] = json!([{
+                "any": [{ "type": "tag", "ids": draft.tag_ids }],
+            }]);
+        }
+        body
+    }
+}
+
+#[async_trait]
+impl BroadcastProvider for KitV4Provider {
+    fn name(&self) -> &'static str {
+        "Kit v4"
+    }
+
+    async fn create_broadcast(&self, draft: &BroadcastDraft) -> Result<Broadcast> {
+        let response = self
+            .client
+            .post(Self::BASE_URL)
+            .json(&Self::body(draft))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(error_response(response).await);
+        }
+        Self::broadcast(&response.json::<Value>().await?["broadcast"])
+    }
+
+    async fn update_broadcast(&self, id: &str, draft: &BroadcastDraft) -> Result<()> {
+        let response = self
+            .client
+            .put(format!("{}/{id}", Self::BASE_URL))
+            .json(&Self::body(draft))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(error_response(response).await);
+        }
+        Ok(())
+    }
+
+    async fn list_scheduled_broadcasts(&self, subject_prefixes: &[&str]) -> Result<Vec<Broadcast>> {
+        let now = Utc::now();
+        let mut scheduled = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut request = self
+                .client
+                .get(Self::BASE_URL)
+                .query(&[("per_page", "500")]);
+            if let Some(ref cursor) = cursor {
+                request = request.query(&[("after", cursor)]);
+            }
+            let page = request
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Value>()
+                .await?;
+            for broadcast in page["broadcasts"]
+                .as_array()
+                .context("missing broadcasts field")?
+            {
+                if broadcast["published_at"].is_null()
+                    && parse_send_at(&broadcast["send_at"]).is_some_and(|send_at| send_at > now)
+                    && has_prefix(
+                        broadcast["subject"].as_str().unwrap_or_default(),
+                        subject_prefixes,
+                    )
+                {
+                    scheduled.push(Self::broadcast(broadcast)?);
+                }
+            }
+            if page["pagination"]["has_next_page"].as_bool() != Some(true) {
+                break;
+            }
+            cursor = page["pagination"]["end_cursor"].as_str().map(String::from);
+        }
+        Ok(scheduled)
+    }
+
+    async fn delete_broadcast(&self, id: &str) -> Result<()> {
+        let response = self
+            .client
+            .delete(format!("{}/{id}", Self::BASE_URL))
+            .send()
+            .await?;
+        match response.status().as_u16() {
+            200..=299 => Ok(()),
+            code => bail!("Failed to delete broadcast {id}: HTTP {code}"),
+        }
+    }
+}
+
+/// Kit (formerly ConvertKit) API v3, which takes the API secret as a query/body param.
+/// https://developers.kit.com/v3#broadcasts
+pub struct KitV3Provider {
+    client: Client,
+    api_secret: String,
+}
+
+impl KitV3Provider {
+    const BASE_URL: &'static str = "https://api.convertkit.com/v3/broadcasts";
+
+    pub fn new(api_secret: &str) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+        let client = Client::builder().default_headers(headers).build()?;
+        Ok(Self {
+            client,
+            api_secret: api_secret.to_string(),
+        })
+    }
+
+    fn body(&self, draft: &BroadcastDraft) -> Result<Value> {
+        if !draft.tag_ids.is_empty() {
+            bail!(
+                "Kit's v3 API can't send broadcasts to tags, set KIT_V4_API_TOKEN to use segments"
+            );
+        }
+        Ok(json!({
+            "api_secret": &self.api_secret,
+            "email_layout_template": Value::Null, // use default template
+            "content": draft.content,
+            "description": draft.description,
+            "subject": draft.subject,
+            "send_at": draft.send_at.to_rfc3339(),
+            "public": true, // false == draft
+        }))
+    }
+}
+
+#[async_trait]
+impl BroadcastProvider for KitV3Provider {
+    fn name(&self) -> &'static str {
+        "Kit v3"
+    }
+
+    /// https://developers.kit.com/v3#create-a-broadcast
+    async fn create_broadcast(&self, draft: &BroadcastDraft) -> Result<Broadcast> {
+        let response = self
+            .client
+            .post(Self::BASE_URL)
+            .json(&self.body(draft)?)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(error_response(response).await);
+        }
+        let json = response.json::<Value>().await?;
+        Ok(Broadcast {
+            id: json["broadcast"]["id"]
+                .as_i64()
+                .context("missing broadcast id")?
+                .to_string(),
+            subject: draft.subject.clone(),
+            send_at: Some(draft.send_at),
+            description: Some(draft.description.clone()),
+        })
+    }
+
+    /// https://developers.kit.com/v3#update-a-broadcast
+    async fn update_broadcast(&self, id: &str, draft: &BroadcastDraft) -> Result<()> {
+        let response = self
+            .client
+            .put(format!("{}/{id}", Self::BASE_URL))
+            .json(&self.body(draft)?)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(error_response(response).await);
+        }
+        Ok(())
+    }
+
+    /// v3 only lists IDs, subjects and creation times, so send times take a
+    /// request per broadcast. Those are only made for broadcasts with a matching
+    /// subject created within [`V3_SCHEDULE_HORIZON_DAYS`]. Broadcasts are listed
+    /// newest-first, so paging stops at the first one older than that.
+    /// https://developers.kit.com/v3#list-broadcasts
+    async fn list_scheduled_broadcasts(&self, subject_prefixes: &[&str]) -> Result<Vec<Broadcast>> {
+        let now = Utc::now();
+        let horizon = now - chrono::Duration::days(V3_SCHEDULE_HORIZON_DAYS);
+        let mut scheduled = Vec::new();
+        let mut page = 1;
+        'outer: loop {
+            let broadcasts = self
+                .client
+                .get(Self::BASE_URL)
+                .query(&[
+                    ("api_secret", &self.api_secret),
+                    ("page", &page.to_string()),
+                    ("sort_order", &"desc".to_string()),
+                ])
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Value>()
+                .await?["broadcasts"]
+                .as_array()
+                .context("missing broadcasts field")?
+                .to_vec();
+            if broadcasts.is_empty() {
+                break;
+            }
+            for broadcast in &broadcasts {
+                if parse_send_at(&broadcast["created_at"])
+                    .is_some_and(|created_at| created_at < horizon)
+                {
+                    break 'outer;
+                }
+                let subject = broadcast["subject"].as_str().unwrap_or("(no subject)");
+                if !has_prefix(subject, subject_prefixes) {
+                    continue;
+                }
+                let id = broadcast["id"].as_i64().context("missing broadcast id")?;
+                let details = self
+                    .client
+                    .get(format!("{}/{id}", Self::BASE_URL))
+                    .query(&[("api_secret", &self.api_secret)])
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<Value>()
+                    .await?;
+                let send_at = parse_send_at(&details["broadcast"]["send_at"]);
+                if send_at.is_none_or(|send_at| send_at <= now) {
+                    // Sent, or a draft
+                    continue;
+                }
+                scheduled.push(Broadcast {
+                    id: id.to_string(),
+                    subject: subject.to_string(),
+                    send_at,
+                    description: details["broadcast"]["description"]
+                        .as_str()
+                        .map(String::from),
+                });
+            }
+            page += 1;
+        }
+        Ok(scheduled)
+    }
+
+    /// https://developers.kit.com/v3#destroy-a-broadcast
+    async fn delete_broadcast(&self, id: &str) -> Result<()> {
+        let response = self
+            .client
+            .delete(format!("{}/{id}", Self::BASE_URL))
+            .query(&[("api_secret", &self.api_secret)])
+            .send()
+            .await?;
+        match response.status().as_u16() {
+            200..=299 => Ok(()),
+            422 => bail!("broadcast {id} was already sent"),
+            code => bail!("Failed to delete broadcast {id}: HTTP {code}"),
+        }
+    }
+}
+
+/// Writes each broadcast to a directory as a `.eml` file (openable in any mail
+/// client) with HTML and plain text versions, plus a `.html` file (openable in a
+/// browser) and a `.txt` file, instead of sending anything.
+/// Useful for testing and for previewing emails without a Kit account.
+pub struct LocalProvider {
+    dir: String,
+}
+
+impl LocalProvider {
+    pub fn new(dir: &str) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {dir}"))?;
+        Ok(Self {
+            dir: dir.to_string(),
+        })
+    }
+
+    /// Pulls a header value out of a `.eml` file written by `create_broadcast`.
+    fn header(eml: &str, name: &str) -> Option<String> {
+        eml.lines()
+            .take_while(|line| !line.is_empty())
+            .find_map(|line| line.strip_prefix(&format!("{name}: ")))
+            .map(String::from)
+    }
+
+    fn write(&self, id: &str, draft: &BroadcastDraft) -> Result<()> {
+        let boundary = "=_meleemajors_alternative";
+        let eml = [
+            "From: meleemajors.gg <hello@meleemajors.gg>".to_string(),
+            "To: subscribers".to_string(),
+            format!("Subject: {}", draft.subject),
+            format!("Date: {}", Utc::now().to_rfc2822()),
+            format!("X-Send-At: {}", draft.send_at.to_rfc3339()),
+            format!("X-Description: {}", draft.description),
+            format!("X-Tag-Ids: {}", draft.tag_ids.iter().join(", ")),
+            "MIME-Version: 1.0".to_string(),
+            format!("Content-Type: multipart/alternative; boundary=\"{boundary}\""),
+            String::new(),
+            // Least preferred first, so mail clients show the HTML
+            format!("--{boundary}"),
+            "Content-Type: text/plain; charset=utf-8".to_string(),
+            "Content-Transfer-Encoding: 8bit".to_string(),
+            String::new(),
+            draft.text_content.clone(),
+            format!("--{boundary}"),
+            "Content-Type: text/html; charset=utf-8".to_string(),
+            "Content-Transfer-Encoding: 8bit".to_string(),
+            String::new(),
+            draft.content.clone(),
+            format!("--{boundary}--"),
+        ]
+        .join("\r\n");
+        fs::write(format!("{}/{id}.eml", self.dir), eml)?;
+        fs::write(format!("{}/{id}.html", self.dir), &draft.content)?;
+        fs::write(format!("{}/{id}.txt", self.dir), &draft.text_content)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BroadcastProvider for LocalProvider {
+    fn name(&self) -> &'static str {
+        "local outbox"
+    }
+
+    async fn create_broadcast(&self, draft: &BroadcastDraft) -> Result<Broadcast> {
+        // Sorts by send time, and stays unique for two broadcasts at the same time
+        let id = format!(
+            "{}-{}",
+            draft.send_at.format("%Y%m%dT%H%M%SZ"),
+            slug(&draft.subject)
+        );
+        self.write(&id, draft)?;
+        Ok(Broadcast {
+            id,
+            subject: draft.subject.clone(),
+            send_at: Some(draft.send_at),
+            description: Some(draft.description.clone()),
+        })
+    }
+
+    async fn update_broadcast(&self, id: &str, draft: &BroadcastDraft) -> Result<()> {
+        if fs::metadata(format!("{}/{id}.eml", self.dir)).is_err() {
+            bail!("no broadcast {id} in {}", self.dir);
+        }
+        self.write(id, draft)
+    }
+
+    async fn list_scheduled_broadcasts(&self, subject_prefixes: &[&str]) -> Result<Vec<Broadcast>> {
+        let now = Utc::now();
+        let mut scheduled = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("eml") {
+                continue;
+            }
+            let eml = fs::read_to_string(&path)?.replace("\r\n", "\n");
+            let send_at = Self::header(&eml, "X-Send-At")
+                .and_then(|send_at| parse_send_at(&Value::String(send_at)));
+            let subject = Self::header(&eml, "Subject").unwrap_or_default();
+            if send_at.is_some_and(|send_at| send_at > now)
+                && has_prefix(&subject, subject_prefixes)
+            {
+                scheduled.push(Broadcast {
+                    id: path.file_stem().unwrap().to_string_lossy().to_string(),
+                    subject,
+                    send_at,
+                    description: Self::header(&eml, "X-Description"),
+                });
+            }
+        }
+        scheduled.sort_by_key(|broadcast| broadcast.send_at);
+        Ok(scheduled)
+    }
+
+    async fn delete_broadcast(&self, id: &str) -> Result<()> {
+        fs::remove_file(format!("{}/{id}.eml", self.dir))
+            .with_context(|| format!("no broadcast {id} in {}", self.dir))?;
+        fs::remove_file(format!("{}/{id}.html", self.dir)).ok();
+        fs::remove_file(format!("{}/{id}.txt", self.dir)).ok();
+        Ok(())
+    }
+}
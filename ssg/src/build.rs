@@ -0,0 +1,100 @@
+use crate::cli::BuildArgs;
+use crate::paths::Paths;
+use crate::scrape::scrape_live;
+use crate::site::{prepare_out_dir, render_site};
+use crate::utils::{log_error, log_green, log_grey, log_heading, log_red, log_skip, log_warn};
+use crate::{changes, discord, mailing_list, social, webhooks};
+
+/// Scrapes start.gg and writes the site, then schedules emails and sends
+/// Discord posts, social announcements and webhooks for what changed.
+pub async fn main(args: BuildArgs, paths: &Paths) {
+    prepare_out_dir(paths);
+    let config = args
+        .scrape
+        .inputs
+        .load_config(paths)
+        .unwrap_or_else(|e| panic!("{e:#}"));
+
+    let mailing_list = if args.dry_run_email {
+        None
+    } else {
+        mailing_list::MailingListService::new(paths)
+            .inspect_err(|e| {
+                log_warn("email", "Mailing list service init failed");
+                log_warn("email", "Mailing list service init failed");
+                log_warn("email", &format!("{:?}", e));
+            })
+            .ok()
+    };
+
+    let (all_tournament_data, json_featured_players) = scrape_live(&args.scrape, paths).await;
+
+    // Exit early for debugging, without writing anything
+    if args.bail {
+        std::process::exit(0)
+    }
+
+    // Discord posts are worked out against the previous build, which
+    // `render_site` overwrites
+    let previous_payload = changes::read_previous_payload(paths);
+    let payload = render_site(&all_tournament_data, &json_featured_players, &config, paths);
+
+    log_heading("Scheduling email");
+    if args.dry_run_email {
+        mailing_list::write_previews(&all_tournament_data, &config, paths)
+            .inspect_err(|e| {
+                log_error("email", "Failed to write email previews");
+                log_red(&e.to_string());
+            })
+            .ok();
+    } else if let Some(ref service) = mailing_list {
+        service
+            .reconcile_broadcasts(&all_tournament_data, &config, paths)
+            .await
+            .inspect_err(|e| {
+                log_error("email", "Failed to reconcile scheduled broadcasts");
+                log_red(&e.to_string());
+            })
+            .ok();
+    } else {
+        log_warn("email", "skipping email scheduling");
+    }
+
+    log_heading("Posting to Discord");
+    match discord::DiscordNotifier::from_config(&config) {
+        Some(notifier) => {
+            notifier
+                .notify(&all_tournament_data, previous_payload.as_ref(), &config)
+                .await
+        }
+        None => log_skip("discord", "no webhooks configured"),
+    }
+
+    log_heading("Announcing new tournaments");
+    social::announce_new_tournaments(
+        &all_tournament_data,
+        previous_payload.as_ref(),
+        &config,
+        paths,
+        args.dry_run_social,
+    )
+    .await
+    .inspect_err(|e| {
+        log_error("social", "Failed to announce new tournaments");
+        log_red(&e.to_string());
+    })
+    .ok();
+
+    log_heading("Sending webhooks");
+    webhooks::send_events(previous_payload.as_ref(), &payload, &config, paths).await;
+
+    log_next_steps();
+}
+
+fn log_next_steps() {
+    log_green("\n🎉 Finished 🎉\n");
+    log_grey("Next steps:");
+    log_grey("1. git commit & push to main to deploy site");
+    log_grey("2. Review scheduled emails: https://app.kit.com/campaigns");
+    log_grey("3. Preview in web browser with `cargo run -- serve`");
+}
@@ -0,0 +1,71 @@
+use std::fs;
+
+use chrono::{DateTime, Utc};
+use icalendar::{Calendar, Class, Component, Event, EventLike};
+use serde_json::Value;
+
+use crate::paths::Paths;
+use crate::utils::replace_placeholder_values;
+
+/// Adds an all-day event for a scraped tournament to `calendar_ics`.
+pub fn generate_calendar(
+    tournament_data: Value,
+    calendar_ics: &mut Calendar,
+    last_updated: &DateTime<Utc>,
+) -> Calendar {
+    calendar_ics
+        .push(
+            Event::new()
+                .starts(
+                    DateTime::from_timestamp(
+                        tournament_data["start-unix-timestamp"]
+                            .as_number()
+                            .unwrap()
+                            .as_i64()
+                            .unwrap(),
+                        0,
+                    )
+                    .unwrap()
+                    .date_naive(),
+                )
+                .ends(
+                    DateTime::from_timestamp(
+                        tournament_data["end-unix-timestamp"]
+                            .as_number()
+                            .unwrap()
+                            .as_i64()
+                            .unwrap(),
+                        0,
+                    )
+                    .unwrap()
+                    .date_naive(),
+                )
+                .summary(tournament_data["name"].as_str().unwrap())
+                .description(&replace_placeholder_values(
+                    &tournament_data,
+                    "{{start.gg-url}}\n\nattendees: {{entrants}}\n\nnotable entrants:\n\n{{player0}}\n{{player1}}\n{{player2}}\n{{player3}}\n{{player4}}\n{{player5}}\n{{player6}}\n{{player7}}\n",
+                ))
+                .class(Class::Public)
+                // DTSTAMP defaults to the current time, which would change the ICS on every build
+                .timestamp(*last_updated)
+                .location(tournament_data["full-address"].as_str().unwrap())
+                .uid(&uuid::Uuid::new_v5(
+                    &uuid::Uuid::NAMESPACE_DNS,
+                    format!(
+                        "{}@meleemajors.gg",
+                        tournament_data["start.gg-tournament-name"]
+                            .as_str()
+                            .unwrap()
+                    )
+                    .as_bytes(),
+                )
+                .to_string())
+                .done(),
+        )
+        .done()
+}
+
+/// Writes `/calendar.ics`.
+pub fn make_calendar(calendar_ics: Calendar, paths: &Paths) {
+    fs::write(paths.site("calendar.ics"), calendar_ics.to_string()).unwrap();
+}
@@ -0,0 +1,336 @@
+use std::fs;
+
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+
+use crate::api::validate_api_payload;
+use crate::paths::Paths;
+use crate::utils::{log_skip, log_success, log_warn};
+
+/// How long entries stay in `/api/v1/changes.json` before they're dropped.
+const CHANGE_LOG_RETENTION_DAYS: i64 = 30;
+
+/// API fields that only mirror another field, so diffing them would report
+/// every change twice.
+const DEPRECATED_FIELDS: [&str; 3] = ["startggTournamentName", "startggUrl", "startggDetailsUrl"];
+
+/// The difference between two `/api/v1/tournaments.json` payloads, keyed by slug.
+#[derive(Default)]
+pub struct TournamentDiff {
+    pub added: Vec<Value>,
+    pub removed: Vec<Value>,
+    pub changed: Vec<TournamentChange>,
+}
+
+pub struct TournamentChange {
+    pub slug: String,
+    pub name: String,
+    pub fields: Vec<FieldChange>,
+}
+
+pub struct FieldChange {
+    pub field: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+impl TournamentDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The `/api/v1/tournaments.json` published by the previous build, if any.
+pub fn read_previous_payload(paths: &Paths) -> Option<Value> {
+    let path = paths.site("api/v1/tournaments.json");
+    let contents = fs::read_to_string(&path).ok()?;
+    serde_json::from_str(&contents)
+        .inspect_err(|e| log_warn("changes", &format!("ignoring unreadable {path}: {e}")))
+        .ok()
+}
+
+pub fn diff_payloads(previous: &Value, current: &Value) -> TournamentDiff {
+    let previous_tournaments = previous["tournaments"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let current_tournaments = current["tournaments"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let find = |tournaments: &[Value], slug: &Value| -> Option<Value> {
+        tournaments.iter().find(|t| t["slug"] == *slug).cloned()
+    };
+
+    let mut diff = TournamentDiff {
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+
+    for tournament in &current_tournaments {
+        let Some(old) = find(&previous_tournaments, &tournament["slug"]) else {
+            diff.added.push(tournament.clone());
+            continue;
+        };
+
+        let fields: Vec<FieldChange> = tournament
+            .as_object()
+            .unwrap()
+            .iter()
+            .filter(|(field, _)| !DEPRECATED_FIELDS.contains(&field.as_str()))
+            .filter(|(field, value)| old[field.as_str()] != **value)
+            .map(|(field, value)| FieldChange {
+                field: field.clone(),
+                from: old[field.as_str()].clone(),
+                to: value.clone(),
+            })
+            .collect();
+
+        if !fields.is_empty() {
+            diff.changed.push(TournamentChange {
+                slug: tournament["slug"].as_str().unwrap_or_default().to_string(),
+                name: tournament["name"].as_str().unwrap_or_default().to_string(),
+                fields,
+            });
+        }
+    }
+
+    for tournament in &previous_tournaments {
+        if find(&current_tournaments, &tournament["slug"]).is_none() {
+            diff.removed.push(tournament.clone());
+        }
+    }
+
+    diff
+}
+
+/// Prepends `diff` to `/api/v1/changes.json` (when there is anything to record)
+/// and drops entries older than the retention window.
+pub fn make_change_log(
+    schema: &Value,
+    diff: &TournamentDiff,
+    last_updated: &DateTime<Utc>,
+    paths: &Paths,
+) {
+    let path = paths.site("api/v1/changes.json");
+    let previous_entries: Vec<Value> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        .and_then(|log| log["entries"].as_array().cloned())
+        .unwrap_or_default();
+
+    let now = Utc::now();
+    let mut entries: Vec<Value> = Vec::new();
+    if diff.is_empty() {
+        log_skip("changes", "no tournament data changed since the last build");
+    } else {
+        entries.push(change_log_entry(last_updated, diff));
+    }
+
+    let cutoff = now - Duration::days(CHANGE_LOG_RETENTION_DAYS);
+    entries.extend(previous_entries.into_iter().filter(|entry| {
+        entry["timestamp"]
+            .as_str()
+            .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+            .is_some_and(|timestamp| timestamp > cutoff)
+    }));
+
+    let change_log = json!({
+        "lastUpdated": last_updated.to_rfc3339(),
+        "retentionDays": CHANGE_LOG_RETENTION_DAYS,
+        "entries": entries,
+    });
+    validate_api_payload(schema, "#/$defs/changeLog", &change_log);
+    fs::write(&path, serde_json::to_string_pretty(&change_log).unwrap()).unwrap();
+    log_success(
+        "changes",
+        &format!(
+            "wrote /api/v1/changes.json ({} added, {} removed, {} changed)",
+            diff.added.len(),
+            diff.removed.len(),
+            diff.changed.len()
+        ),
+    );
+}
+
+fn change_log_entry(timestamp: &DateTime<Utc>, diff: &TournamentDiff) -> Value {
+    let summary = |t: &Value| json!({ "slug": t["slug"], "name": t["name"] });
+    json!({
+        "timestamp": timestamp.to_rfc3339(),
+        "added": diff.added.iter().map(summary).collect::<Vec<Value>>(),
+        "removed": diff.removed.iter().map(summary).collect::<Vec<Value>>(),
+        "changed": diff.changed.iter().map(|change| json!({
+            "slug": change.slug,
+            "name": change.name,
+            "fields": change.fields.iter().map(|field| json!({
+                "field": field.field,
+                "from": field.from,
+                "to": field.to,
+            })).collect::<Vec<Value>>(),
+        })).collect::<Vec<Value>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(tournaments: Vec<Value>) -> Value {
+        json!({ "tournaments": tournaments })
+    }
+
+    fn tournament(slug: &str, name: &str, entrants: u64) -> Value {
+        json!({
+            "slug": slug,
+            "name": name,
+            "entrants": entrants,
+            "startggTournamentName": slug,
+        })
+    }
+
+    fn slugs(tournaments: &[Value]) -> Vec<&str> {
+        tournaments
+            .iter()
+            .map(|t| t["slug"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn diff_finds_added_removed_and_changed_tournaments() {
+        let previous = payload(vec![
+            tournament("genesis", "Genesis", 1000),
+            tournament("sapf2", "SAPF 2", 200),
+            tournament("bigHouse", "The Big House", 900),
+        ]);
+        let mut renamed = tournament("sapf2", "SAPF II", 245);
+        renamed["startggTournamentName"] = json!("sapf-ii");
+        let current = payload(vec![
+            tournament("genesis", "Genesis", 1000),
+            renamed,
+            tournament("fullHouse", "Full House", 300),
+        ]);
+
+        let diff = diff_payloads(&previous, &current);
+
+        assert_eq!(slugs(&diff.added), ["fullHouse"]);
+        assert_eq!(slugs(&diff.removed), ["bigHouse"]);
+        assert_eq!(diff.changed.len(), 1);
+        let change = &diff.changed[0];
+        assert_eq!(
+            (change.slug.as_str(), change.name.as_str()),
+            ("sapf2", "SAPF II")
+        );
+        // Deprecated mirror fields aren't reported
+        let fields: Vec<(&str, &Value, &Value)> = change
+            .fields
+            .iter()
+            .map(|field| (field.field.as_str(), &field.from, &field.to))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("name", &json!("SAPF 2"), &json!("SAPF II")),
+                ("entrants", &json!(200), &json!(245)),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_of_identical_payloads_is_empty() {
+        let tournaments = payload(vec![tournament("genesis", "Genesis", 1000)]);
+        assert!(diff_payloads(&tournaments, &tournaments).is_empty());
+    }
+
+    #[test]
+    fn change_log_prepends_entries_and_drops_old_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::for_test(dir.path());
+        let schema = crate::api::read_api_schema(&paths);
+        let now = Utc::now();
+        let empty = diff_payloads(&payload(vec![]), &payload(vec![]));
+        let recent = now - Duration::days(CHANGE_LOG_RETENTION_DAYS - 1);
+        let expired = now - Duration::days(CHANGE_LOG_RETENTION_DAYS + 1);
+        fs::create_dir_all(paths.site("api/v1")).unwrap();
+        fs::write(
+            paths.site("api/v1/changes.json"),
+            json!({
+                "lastUpdated": recent.to_rfc3339(),
+                "retentionDays": CHANGE_LOG_RETENTION_DAYS,
+                "entries": [
+                    change_log_entry(&recent, &empty),
+                    change_log_entry(&expired, &empty),
+                ],
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let read_timestamps = || -> Vec<String> {
+            let log: Value = serde_json::from_str(
+                &fs::read_to_string(paths.site("api/v1/changes.json")).unwrap(),
+            )
+            .unwrap();
+            log["entries"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["timestamp"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        // Nothing changed: no new entry, but the expired one still goes
+        make_change_log(&schema, &empty, &recent, &paths);
+        assert_eq!(read_timestamps(), [recent.to_rfc3339()]);
+
+        let diff = diff_payloads(
+            &payload(vec![]),
+            &payload(vec![tournament("genesis", "Genesis", 1000)]),
+        );
+        make_change_log(&schema, &diff, &now, &paths);
+        assert_eq!(read_timestamps(), [now.to_rfc3339(), recent.to_rfc3339()]);
+    }
+
+    #[test]
+    fn first_build_writes_an_empty_change_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::for_test(dir.path());
+        let now = Utc::now();
+
+        crate::api::make_api(&[], &now, &paths);
+
+        let log: Value =
+            serde_json::from_str(&fs::read_to_string(paths.site("api/v1/changes.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            log,
+            json!({
+                "lastUpdated": now.to_rfc3339(),
+                "retentionDays": CHANGE_LOG_RETENTION_DAYS,
+                "entries": [],
+            })
+        );
+    }
+
+    #[test]
+    fn missing_previous_payload_keeps_the_change_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::for_test(dir.path());
+        let schema = crate::api::read_api_schema(&paths);
+        let recent = Utc::now() - Duration::days(1);
+        let diff = diff_payloads(
+            &payload(vec![]),
+            &payload(vec![tournament("genesis", "Genesis", 1000)]),
+        );
+        fs::create_dir_all(paths.site("api/v1")).unwrap();
+        make_change_log(&schema, &diff, &recent, &paths);
+
+        // e.g. the published tournaments.json couldn't be downloaded
+        crate::api::make_api(&[], &Utc::now(), &paths);
+
+        let log: Value =
+            serde_json::from_str(&fs::read_to_string(paths.site("api/v1/changes.json")).unwrap())
+                .unwrap();
+        assert_eq!(log["entries"].as_array().unwrap().len(), 1);
+        assert_eq!(log["entries"][0]["timestamp"], recent.to_rfc3339());
+    }
+}
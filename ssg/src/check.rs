@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDateTime;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::cli::InputArgs;
+use crate::config::Config;
+use crate::generate_gql::{featured_players_query, FEATURED_PLAYERS_QUERY_PATH};
+use crate::images;
+use crate::paths::Paths;
+use crate::utils::{log_error, log_heading, log_red, log_success};
+
+/// Every key a tournament in tournaments.json can have (see
+/// sampleTournaments.jsonc).
+const TOURNAMENT_KEYS: [&str; 22] = [
+    "bracketUrl",
+    "top8-start-time",
+    "schedule-url",
+    "stream-url",
+    "name",
+    "player0",
+    "player1",
+    "player2",
+    "player3",
+    "player4",
+    "player5",
+    "player6",
+    "player7",
+    "city-and-state",
+    "location-code",
+    "image-url",
+    "image-crop",
+    "image-focal-point",
+    "thumbnail-url",
+    "thumbnail-crop",
+    "thumbnail-focal-point",
+    "notifications",
+];
+
+/// Checks the hand-edited inputs for the mistakes that would otherwise only
+/// show up halfway through a build. Returns whether everything passed.
+pub fn main(inputs: &InputArgs, paths: &Paths) -> bool {
+    log_heading("Checking inputs");
+    let config = report("config", inputs.load_config(paths));
+    let top_players = report(
+        "topPlayers",
+        check_top_players(&inputs.top_players_path(paths), paths),
+    );
+    let tournaments = report(
+        "tournaments",
+        check_tournaments(&inputs.tournaments_path(paths), config.as_ref(), paths),
+    );
+    config.is_some() && top_players.is_some() && tournaments.is_some()
+}
+
+fn report<T>(label: &str, result: Result<T>) -> Option<T> {
+    match result {
+        Ok(value) => {
+            log_success(label, "ok");
+            Some(value)
+        }
+        Err(e) => {
+            log_error(label, "failed");
+            log_red(&format!("{e:#}"));
+            None
+        }
+    }
+}
+
+/// topPlayers.json is a list of unique names, and getFeaturedPlayers.gql was
+/// regenerated since it last changed.
+fn check_top_players(path: &str, paths: &Paths) -> Result<()> {
+    let contents = fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+    let players: Vec<String> =
+        serde_json::from_str(&contents).context("topPlayers.json must be an array of names")?;
+    let mut seen = HashSet::new();
+    let duplicates: Vec<&str> = players
+        .iter()
+        .filter(|player| !seen.insert(player.to_lowercase()))
+        .map(String::as_str)
+        .collect();
+    if !duplicates.is_empty() {
+        bail!("listed more than once: {}", duplicates.join(", "));
+    }
+
+    let query_path = paths.template(FEATURED_PLAYERS_QUERY_PATH);
+    let query = fs::read_to_string(&query_path).unwrap_or_default();
+    if query != featured_players_query(&contents) {
+        bail!("{FEATURED_PLAYERS_QUERY_PATH} is out of date, run `cargo run -- gql generate`");
+    }
+    Ok(())
+}
+
+/// Every problem with every tournament, rather than just the first.
+fn check_tournaments(path: &str, config: Option<&Config>, paths: &Paths) -> Result<()> {
+    let contents = fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+    let tournaments: Vec<Value> =
+        serde_json::from_str(&contents).context("tournaments.json must be an array")?;
+
+    let bracket_url =
+        Regex::new(r"^(https?://)?(www\.)?start\.gg/tournament/[^/]+/event/[^/]+/?$").unwrap();
+    let mut bracket_urls = HashSet::new();
+    let mut problems = Vec::new();
+    for (i, tournament) in tournaments.iter().enumerate() {
+        let Some(object) = tournament.as_object() else {
+            problems.push(format!("tournament {i}: must be an object"));
+            continue;
+        };
+        let label = match object.get("bracketUrl").and_then(Value::as_str) {
+            Some(url) => {
+                if !bracket_url.is_match(url) {
+                    problems.push(format!(
+                        "{url}: bracketUrl must look like https://start.gg/tournament/<name>/event/<event>"
+                    ));
+                }
+                if !bracket_urls.insert(url.trim_end_matches('/')) {
+                    problems.push(format!("{url}: listed more than once"));
+                }
+                url.to_string()
+            }
+            None => {
+                problems.push(format!("tournament {i}: missing bracketUrl"));
+                format!("tournament {i}")
+            }
+        };
+        for key in object.keys() {
+            if !TOURNAMENT_KEYS.contains(&key.as_str()) {
+                problems.push(format!("{label}: unknown key {key:?}"));
+            }
+        }
+        for problem in check_overrides(tournament, config, paths) {
+            problems.push(format!("{label}: {problem:#}"));
+        }
+    }
+
+    if !problems.is_empty() {
+        bail!("{} problems:\n{}", problems.len(), problems.join("\n"));
+    }
+    Ok(())
+}
+
+/// Overrides that would fail the build, or be silently ignored.
+fn check_overrides(
+    tournament: &Value,
+    config: Option<&Config>,
+    paths: &Paths,
+) -> Vec<anyhow::Error> {
+    let mut problems = Vec::new();
+    if let Some(top8_start_time) = tournament["top8-start-time"].as_str() {
+        if !top8_start_time.is_empty()
+            && NaiveDateTime::parse_from_str(top8_start_time, "%Y-%m-%d %I:%M%P").is_err()
+        {
+            problems.push(anyhow::anyhow!(
+                "top8-start-time {top8_start_time:?} must look like 2024-10-06 3:00PM"
+            ));
+        }
+    }
+    if let Some(config) = config {
+        if let Err(e) = config.notifications.for_tournament(tournament) {
+            problems.push(e);
+        }
+    }
+    for (prefix, aspect_ratio) in [("image", images::CARD_ASPECT_RATIO), ("thumbnail", 1.0)] {
+        if let Some(url) = tournament[format!("{prefix}-url")].as_str() {
+            if let Err(e) = check_local_file(url, paths) {
+                problems.push(e.context(format!("bad {prefix}-url")));
+            }
+        }
+        if let Err(e) = images::ImageHints::from_overrides(tournament, prefix, aspect_ratio) {
+            problems.push(e);
+        }
+    }
+    if let Some(url) = tournament["schedule-url"].as_str() {
+        if !url.is_empty() && !url.starts_with("http") {
+            if let Err(e) = check_local_file(url, paths) {
+                problems.push(e.context("bad schedule-url"));
+            }
+        }
+    }
+    problems
+}
+
+/// A URL, or a path to an existing file under `site/assets`.
+fn check_local_file(value: &str, paths: &Paths) -> Result<()> {
+    if let images::ImageSource::Local(path) = images::ImageSource::from_override(value)? {
+        if !Path::new(&paths.static_file(&path)).is_file() {
+            bail!("site/{path} does not exist");
+        }
+    }
+    Ok(())
+}
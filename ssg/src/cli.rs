@@ -0,0 +1,207 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+
+use crate::config::Config;
+use crate::paths::Paths;
+
+/// Builds meleemajors.gg from tournaments.json and start.gg.
+///
+/// Env vars can also go in `ssg/.env` (see `.env.example`).
+#[derive(Parser)]
+#[command(name = "ssg", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+    #[command(flatten)]
+    pub paths: PathArgs,
+}
+
+/// Where to read and write files, for every command. See `Paths`.
+#[derive(Args)]
+pub struct PathArgs {
+    /// A JSON file setting any of the directories below, as `data-dir`,
+    /// `template-dir` and so on. Relative paths are relative to the file
+    #[arg(
+        long,
+        global = true,
+        env = "MELEEMAJORS_BUILD_CONFIG",
+        value_name = "FILE"
+    )]
+    pub build_config: Option<PathBuf>,
+    /// Where tournaments.json, topPlayers.json and config.json are, instead of `ssg/src`
+    #[arg(long, global = true, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Where the `html/` and `graphql/` templates are, instead of `ssg/src`
+    #[arg(long, global = true, value_name = "DIR")]
+    pub template_dir: Option<PathBuf>,
+    /// Where card images are kept between builds, instead of `ssg/cache`
+    #[arg(long, global = true, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// Where the site's hand-written files are, instead of `site/`
+    #[arg(long, global = true, value_name = "DIR")]
+    pub static_dir: Option<PathBuf>,
+    /// Where to write (or serve) the site, instead of `site/`. The static
+    /// files are copied there first
+    #[arg(long, global = true, value_name = "DIR")]
+    pub out_dir: Option<PathBuf>,
+    /// Where email and social post previews go, instead of the repo root
+    #[arg(long, global = true, value_name = "DIR")]
+    pub preview_dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Scrape start.gg and write the site, API, calendars and feeds, then
+    /// schedule emails and send notifications
+    Build(BuildArgs),
+    /// Manage topPlayers.json
+    Rankings {
+        #[command(subcommand)]
+        command: RankingsCommand,
+    },
+    /// Manage the generated GraphQL queries
+    Gql {
+        #[command(subcommand)]
+        command: GqlCommand,
+    },
+    /// Manage the generated API client types
+    Types {
+        #[command(subcommand)]
+        command: TypesCommand,
+    },
+    /// Check tournaments.json, topPlayers.json and config.json for mistakes,
+    /// without scraping start.gg
+    Check(InputArgs),
+    /// Preview emails
+    Email {
+        #[command(subcommand)]
+        command: EmailCommand,
+    },
+    /// Serve the site over HTTP, re-rendering it from the last build's start.gg
+    /// responses whenever the templates or inputs change, and reloading the
+    /// browser
+    Serve(ServeArgs),
+}
+
+#[derive(Subcommand)]
+pub enum RankingsCommand {
+    /// Replace the top of topPlayers.json with the latest SSBMRank from
+    /// Liquipedia, keeping players who dropped off
+    Update {
+        #[arg(long, value_name = "FILE")]
+        top_players: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum GqlCommand {
+    /// Regenerate getFeaturedPlayers.gql from topPlayers.json
+    Generate {
+        #[arg(long, value_name = "FILE")]
+        top_players: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TypesCommand {
+    /// Regenerate tournaments.d.ts and the Rust API types from tournaments.schema.json
+    Generate,
+}
+
+#[derive(Subcommand)]
+pub enum EmailCommand {
+    /// Scrape start.gg and write the emails that would be scheduled to
+    /// email-previews/, without touching the email provider
+    Preview(ScrapeArgs),
+}
+
+/// The files a build reads, which default to the ones in the data directory.
+#[derive(Args, Clone, Default)]
+pub struct InputArgs {
+    /// The tournaments to list
+    #[arg(long, value_name = "FILE")]
+    pub tournaments: Option<PathBuf>,
+    /// Ranked players, best first
+    #[arg(long, value_name = "FILE")]
+    pub top_players: Option<PathBuf>,
+    /// Email, notification and integration settings
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+}
+
+impl InputArgs {
+    pub fn tournaments_path(&self, paths: &Paths) -> String {
+        input_path(&self.tournaments, paths, "tournaments.json")
+    }
+
+    pub fn top_players_path(&self, paths: &Paths) -> String {
+        input_path(&self.top_players, paths, "topPlayers.json")
+    }
+
+    pub fn config_path(&self, paths: &Paths) -> String {
+        input_path(&self.config, paths, "config.json")
+    }
+
+    pub fn load_config(&self, paths: &Paths) -> Result<Config> {
+        Config::load(&self.config_path(paths))
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct ScrapeArgs {
+    #[command(flatten)]
+    pub inputs: InputArgs,
+    /// start.gg API token, from https://start.gg/admin/profile/developer
+    #[arg(long, env = "STARTGGAPI", hide_env_values = true, value_parser = parse_startgg_token)]
+    pub startgg_token: String,
+}
+
+#[derive(Args, Clone)]
+pub struct BuildArgs {
+    #[command(flatten)]
+    pub scrape: ScrapeArgs,
+    /// Stop after scraping without writing anything, for debugging
+    #[arg(long)]
+    pub bail: bool,
+    /// Write the emails that would be scheduled to email-previews/ instead of
+    /// scheduling them
+    #[arg(long)]
+    pub dry_run_email: bool,
+    /// Write the Bluesky and Mastodon posts for new tournaments to
+    /// social-previews/ instead of posting them
+    #[arg(long)]
+    pub dry_run_social: bool,
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    #[command(flatten)]
+    pub inputs: InputArgs,
+    #[arg(long, default_value_t = 8000)]
+    pub port: u16,
+    /// Don't open the site in a browser
+    #[arg(long)]
+    pub no_open: bool,
+}
+
+/// `path` if it was given on the command line, otherwise `default` in the
+/// data directory.
+pub fn input_path(path: &Option<PathBuf>, paths: &Paths, default: &str) -> String {
+    match path {
+        Some(path) => path.to_str().unwrap().to_string(),
+        None => paths.data(default),
+    }
+}
+
+fn parse_startgg_token(token: &str) -> Result<String, String> {
+    let token = token.trim();
+    if token.is_empty() {
+        return Err("STARTGGAPI is set but empty. Check the GitHub Actions secret value.".into());
+    }
+    if token.contains('=') || token.starts_with("STARTGGAPI") {
+        return Err("STARTGGAPI should contain only the token value, not `STARTGGAPI=...`.".into());
+    }
+    Ok(token.to_string())
+}
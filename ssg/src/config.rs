@@ -0,0 +1,473 @@
+use std::fmt::{self, Display};
+use std::fs;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Site settings that aren't tournament data, from `config.json`.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub digest: DigestConfig,
+    #[serde(default)]
+    pub segments: SegmentsConfig,
+    #[serde(default)]
+    pub notifications: NotificationPolicy,
+    #[serde(default)]
+    pub discord: DiscordConfig,
+    #[serde(default)]
+    pub social: SocialConfig,
+    /// Endpoints that get tournament events as they're found
+    #[serde(default)]
+    pub webhooks: Vec<WebhookEndpoint>,
+}
+
+/// The weekly email listing every tournament coming up soon.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields, default)]
+pub struct DigestConfig {
+    pub enabled: bool,
+    /// e.g. `"Mon"` or `"monday"`
+    #[serde(deserialize_with = "from_str")]
+    pub weekday: Weekday,
+    /// 24 hour `"HH:MM"`, in `timezone`
+    #[serde(deserialize_with = "from_str")]
+    pub time: NaiveTime,
+    #[serde(deserialize_with = "from_str")]
+    pub timezone: Tz,
+    /// How far ahead of the send time to list tournaments
+    pub weeks: u32,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            weekday: Weekday::Mon,
+            time: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+            timezone: chrono_tz::America::New_York,
+            weeks: 3,
+        }
+    }
+}
+
+/// Lets subscribers pick which tournament emails they get, using Kit tags.
+/// Tag IDs come from https://app.kit.com/subscribers (filter by tag, the ID is
+/// in the URL).
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields, default)]
+pub struct SegmentsConfig {
+    /// When off, every email goes to every subscriber and the signup form has
+    /// no preferences
+    pub enabled: bool,
+    /// Subscribers with this tag get every tournament email. Tag all existing
+    /// subscribers with it before turning segments on
+    pub everything_tag: Option<u64>,
+    pub regions: Vec<RegionSegment>,
+    pub players: Vec<PlayerSegment>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RegionSegment {
+    pub name: String,
+    /// Not offered in the signup form until it's set
+    pub tag: Option<u64>,
+    /// ISO country codes (`"CA"`), or country and state codes (`"US-CA"`)
+    pub locations: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PlayerSegment {
+    /// Matched case-insensitively against a tournament's featured players
+    pub name: String,
+    pub tag: u64,
+}
+
+/// Posts to Discord channels through their webhooks, at the same times as the
+/// emails (see `notifications`) and when the tournament list changes.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields, default)]
+pub struct DiscordConfig {
+    pub enabled: bool,
+    pub webhooks: Vec<DiscordWebhook>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DiscordWebhook {
+    /// For logging
+    pub name: String,
+    /// The env var holding the webhook URL, since anyone with it can post
+    pub url_env: String,
+    /// Reminders, top 8 and registration closing
+    #[serde(default = "enabled")]
+    pub notifications: bool,
+    /// New tournaments and newly registered featured players
+    #[serde(default = "enabled")]
+    pub changes: bool,
+}
+
+/// Announces newly added tournaments on Bluesky and Mastodon. The passwords
+/// and tokens come from env vars (see `.env.example`).
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields, default)]
+pub struct SocialConfig {
+    pub enabled: bool,
+    pub bluesky: Option<BlueskyConfig>,
+    pub mastodon: Option<MastodonConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BlueskyConfig {
+    /// The account's PDS, e.g. `"https://bsky.social"`
+    pub service: String,
+    /// e.g. `"meleemajors.gg"`
+    pub handle: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MastodonConfig {
+    /// e.g. `"https://mastodon.social"`
+    pub instance: String,
+}
+
+/// Gets a signed JSON request (see `webhookEvent` in tournaments.schema.json)
+/// for each event, found by diffing against the previous build.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WebhookEndpoint {
+    /// For logging and the delivery log
+    pub name: String,
+    pub url: String,
+    /// The env var holding the shared secret requests are signed with
+    pub secret_env: String,
+    /// Defaults to all of them
+    #[serde(default = "WebhookEvent::all")]
+    pub events: Vec<WebhookEvent>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebhookEvent {
+    /// New since the previous build
+    Added,
+    /// Any API field differs from the previous build
+    Changed,
+    /// Taken off the schedule before it ended
+    Removed,
+    /// Started since the previous build
+    Started,
+    /// Ended since the previous build
+    Ended,
+}
+
+impl WebhookEvent {
+    fn all() -> Vec<Self> {
+        vec![
+            Self::Added,
+            Self::Changed,
+            Self::Removed,
+            Self::Started,
+            Self::Ended,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Changed => "changed",
+            Self::Removed => "removed",
+            Self::Started => "started",
+            Self::Ended => "ended",
+        }
+    }
+}
+
+/// When each kind of tournament email is sent. Tournaments can override any
+/// of the kinds with a `notifications` object in tournaments.json, e.g.
+/// `{ "reminder": { "offsets": ["7d", "1d"] }, "top8": { "enabled": false } }`.
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields, default)]
+pub struct NotificationPolicy {
+    /// Before the tournament starts
+    pub reminder: NotificationKindPolicy,
+    /// Before `top8-start-time`, which is required for these
+    pub top8: NotificationKindPolicy,
+    /// Before start.gg's registration close time
+    pub registration_closing: NotificationKindPolicy,
+    /// Only set globally, not per tournament
+    pub quiet_hours: Option<QuietHours>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NotificationKindPolicy {
+    pub enabled: bool,
+    /// How long before the event to send at, one email per offset
+    pub offsets: Vec<Offset>,
+}
+
+/// A per-tournament override, where anything left out keeps the global value.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields, default)]
+struct NotificationOverrides {
+    reminder: NotificationKindOverride,
+    top8: NotificationKindOverride,
+    registration_closing: NotificationKindOverride,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields, default)]
+struct NotificationKindOverride {
+    enabled: Option<bool>,
+    offsets: Option<Vec<Offset>>,
+}
+
+/// Reminders that would be sent from `start` up to (but not including) `end`,
+/// which can wrap past midnight, are sent at `end` instead.
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct QuietHours {
+    #[serde(deserialize_with = "from_str")]
+    pub start: NaiveTime,
+    #[serde(deserialize_with = "from_str")]
+    pub end: NaiveTime,
+    /// Where most subscribers are
+    #[serde(deserialize_with = "from_str")]
+    pub timezone: Tz,
+}
+
+/// A lead time like `"5d"`, `"12h"` or `"30m"`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Offset(pub Duration);
+
+impl Default for NotificationPolicy {
+    fn default() -> Self {
+        let kind = |enabled, offset| NotificationKindPolicy {
+            enabled,
+            offsets: vec![Offset(offset)],
+        };
+        Self {
+            reminder: kind(true, Duration::days(5)),
+            top8: kind(true, Duration::zero()),
+            registration_closing: kind(false, Duration::days(2)),
+            quiet_hours: None,
+        }
+    }
+}
+
+impl NotificationPolicy {
+    /// The policy for one tournament, with its `notifications` overrides applied.
+    pub fn for_tournament(&self, tournament_data: &Value) -> Result<Self> {
+        let overrides: NotificationOverrides = match &tournament_data["notifications"] {
+            Value::Null => return Ok(self.clone()),
+            overrides => serde_json::from_value(overrides.clone())
+                .context("invalid notifications override")?,
+        };
+        let apply = |kind: &NotificationKindPolicy, kind_override: NotificationKindOverride| {
+            NotificationKindPolicy {
+                enabled: kind_override.enabled.unwrap_or(kind.enabled),
+                offsets: kind_override.offsets.unwrap_or(kind.offsets.clone()),
+            }
+        };
+        Ok(Self {
+            reminder: apply(&self.reminder, overrides.reminder),
+            top8: apply(&self.top8, overrides.top8),
+            registration_closing: apply(&self.registration_closing, overrides.registration_closing),
+            quiet_hours: self.quiet_hours.clone(),
+        })
+    }
+}
+
+impl QuietHours {
+    /// `send_at`, or the end of the quiet hours it falls in.
+    pub fn adjust<T: TimeZone>(&self, send_at: DateTime<T>) -> DateTime<Tz> {
+        let local = send_at.with_timezone(&self.timezone);
+        let time = local.time();
+        let quiet = if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        };
+        if !quiet {
+            return local;
+        }
+        // Before midnight in quiet hours that wrap, they end the next day
+        let end_date = if time >= self.end {
+            local.date_naive() + Duration::days(1)
+        } else {
+            local.date_naive()
+        };
+        let end = end_date.and_time(self.end);
+        // An end skipped by DST (e.g. 2:30am) is pushed an hour later
+        [end, end + Duration::hours(1)]
+            .into_iter()
+            .find_map(|end| end.and_local_timezone(self.timezone).earliest())
+            .unwrap_or(local)
+    }
+}
+
+impl FromStr for Offset {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let error = || anyhow!("expected a number followed by d, h or m");
+        let unit = value.chars().last().ok_or_else(error)?;
+        let amount: i64 = value[..value.len() - unit.len_utf8()]
+            .parse()
+            .map_err(|_| error())?;
+        match unit {
+            'd' => Ok(Offset(Duration::days(amount))),
+            'h' => Ok(Offset(Duration::hours(amount))),
+            'm' => Ok(Offset(Duration::minutes(amount))),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Offset {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        from_str(deserializer)
+    }
+}
+
+/// The shortest form that parses back to the same offset, e.g. `"2d"` for 48 hours.
+impl Display for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let minutes = self.0.num_minutes();
+        if minutes % (24 * 60) == 0 {
+            write!(f, "{}d", minutes / (24 * 60))
+        } else if minutes % 60 == 0 {
+            write!(f, "{}h", minutes / 60)
+        } else {
+            write!(f, "{minutes}m")
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self> {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+        let config: Self = serde_json::from_str(&contents).context("invalid config.json")?;
+        if config.segments.enabled && config.segments.everything_tag.is_none() {
+            bail!("config.json: segments.everything-tag is required when segments are enabled");
+        }
+        Ok(config)
+    }
+}
+
+impl SegmentsConfig {
+    /// The first region containing `location_code` (see `location-code` in
+    /// tournament data), if any.
+    pub fn region(&self, location_code: &str) -> Option<&RegionSegment> {
+        let country = location_code.split('-').next().unwrap_or_default();
+        self.regions.iter().find(|region| {
+            region
+                .locations
+                .iter()
+                .any(|location| location == location_code || location == country)
+        })
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Deserializes any string field with a `FromStr` type, like weekdays and timezones.
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = String::deserialize(deserializer)?;
+    value
+        .parse()
+        .map_err(|e| serde::de::Error::custom(format!("{value:?}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet_hours(start: &str, end: &str) -> QuietHours {
+        QuietHours {
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+            timezone: chrono_tz::America::New_York,
+        }
+    }
+
+    /// Adjusts a New York time, both as `"YYYY-MM-DD HH:MM"`
+    fn adjust(quiet_hours: &QuietHours, local: &str) -> String {
+        let send_at = chrono::NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M")
+            .unwrap()
+            .and_local_timezone(quiet_hours.timezone)
+            .earliest()
+            .unwrap();
+        quiet_hours
+            .adjust(send_at)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    }
+
+    #[test]
+    fn quiet_hours_that_wrap_past_midnight_end_the_next_morning() {
+        let overnight = quiet_hours("23:00", "08:00");
+        assert_eq!(adjust(&overnight, "2026-10-19 22:59"), "2026-10-19 22:59");
+        assert_eq!(adjust(&overnight, "2026-10-19 23:00"), "2026-10-20 08:00");
+        assert_eq!(adjust(&overnight, "2026-10-19 23:30"), "2026-10-20 08:00");
+        assert_eq!(adjust(&overnight, "2026-10-20 02:00"), "2026-10-20 08:00");
+        assert_eq!(adjust(&overnight, "2026-10-20 08:00"), "2026-10-20 08:00");
+    }
+
+    #[test]
+    fn quiet_hours_within_a_day_end_the_same_day() {
+        let early = quiet_hours("01:00", "06:00");
+        assert_eq!(adjust(&early, "2026-10-19 00:59"), "2026-10-19 00:59");
+        assert_eq!(adjust(&early, "2026-10-19 01:00"), "2026-10-19 06:00");
+        assert_eq!(adjust(&early, "2026-10-19 05:59"), "2026-10-19 06:00");
+        assert_eq!(adjust(&early, "2026-10-19 06:00"), "2026-10-19 06:00");
+        assert_eq!(adjust(&early, "2026-10-19 23:00"), "2026-10-19 23:00");
+    }
+
+    #[test]
+    fn quiet_hours_ending_in_a_dst_gap_end_an_hour_later() {
+        // Clocks skip from 2am to 3am on March 8
+        let overnight = quiet_hours("23:00", "02:30");
+        assert_eq!(adjust(&overnight, "2026-03-08 01:00"), "2026-03-08 03:30");
+        assert_eq!(adjust(&overnight, "2026-03-07 23:15"), "2026-03-08 03:30");
+        // ...and on other nights, end as usual
+        assert_eq!(adjust(&overnight, "2026-03-09 01:00"), "2026-03-09 02:30");
+    }
+
+    #[test]
+    fn offsets_parse_and_display() {
+        for (value, duration) in [
+            ("5d", Duration::days(5)),
+            ("12h", Duration::hours(12)),
+            ("30m", Duration::minutes(30)),
+            ("0m", Duration::zero()),
+        ] {
+            let offset: Offset = value.parse().unwrap();
+            assert!(offset == Offset(duration), "{value}");
+            assert_eq!(offset.to_string(), if value == "0m" { "0d" } else { value });
+        }
+        assert_eq!(Offset(Duration::hours(48)).to_string(), "2d");
+        assert_eq!(Offset(Duration::minutes(90)).to_string(), "90m");
+        for value in ["", "5", "d", "5w", "1.5d", "five d", "5dd"] {
+            assert!(value.parse::<Offset>().is_err(), "{value:?}");
+        }
+    }
+}
@@ -0,0 +1,591 @@
+use std::env;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use reqwest::{Client, StatusCode, Url};
+use serde_json::{json, Value};
+use tokio::time::sleep;
+
+use crate::config::Config;
+use crate::mailing_list::{plan_notification, BroadcastKind, PlannedNotification};
+use crate::utils::{absolute_url, log_error, log_red, log_skip, log_success, log_warn};
+
+/// How many times a post is tried when Discord rate limits it.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Posts tournament embeds to the Discord webhooks in `config.json`.
+///
+/// Webhooks can't schedule messages, so instead each build posts every
+/// notification whose send time passed since the previous build (going by the
+/// previous `/api/v1/tournaments.json`'s `generatedAt`). They're only as
+/// punctual as the builds are frequent.
+pub struct DiscordNotifier {
+    client: Client,
+    webhooks: Vec<Webhook>,
+}
+
+struct Webhook {
+    name: String,
+    url: Url,
+    notifications: bool,
+    changes: bool,
+}
+
+/// Which of a webhook's settings a post falls under.
+#[derive(Clone, Copy)]
+enum PostKind {
+    Notification,
+    Change,
+}
+
+struct Post {
+    /// e.g. "reminder (5d before) for Genesis X", for logging
+    label: String,
+    kind: PostKind,
+    body: Value,
+}
+
+impl DiscordNotifier {
+    /// The configured webhooks whose URL env var is set, or `None` if there
+    /// aren't any (or Discord is turned off).
+    pub fn from_config(config: &Config) -> Option<Self> {
+        Self::from_config_with(config, |name| env::var(name).ok())
+    }
+
+    /// Like `from_config`, looking webhook URLs up with `env_var` instead of in
+    /// the environment.
+    fn from_config_with(config: &Config, env_var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        if !config.discord.enabled {
+            return None;
+        }
+        let webhooks: Vec<Webhook> = config
+            .discord
+            .webhooks
+            .iter()
+            .filter_map(|webhook| {
+                // Unset secrets come through as empty in GitHub Actions
+                let Some(url) = env_var(&webhook.url_env).filter(|url| !url.trim().is_empty())
+                else {
+                    log_warn(
+                        "discord",
+                        &format!("{} skipped: {} isn't set", webhook.name, webhook.url_env),
+                    );
+                    return None;
+                };
+                let mut url = Url::parse(url.trim())
+                    .inspect_err(|e| {
+                        log_warn(
+                            "discord",
+                            &format!("{} skipped: invalid {}: {e}", webhook.name, webhook.url_env),
+                        );
+                    })
+                    .ok()?;
+                // Wait for the message to be created, so failures show up in
+                // the response, and allow link buttons on non-bot webhooks
+                url.query_pairs_mut()
+                    .append_pair("wait", "true")
+                    .append_pair("with_components", "true");
+                Some(Webhook {
+                    name: webhook.name.clone(),
+                    url,
+                    notifications: webhook.notifications,
+                    changes: webhook.changes,
+                })
+            })
+            .collect();
+        if webhooks.is_empty() {
+            return None;
+        }
+        Some(Self {
+            client: Client::new(),
+            webhooks,
+        })
+    }
+
+    /// Posts the notifications that came due since the previous build, plus
+    /// notices for tournaments added since then and featured players who
+    /// registered since then. Nothing is posted without a previous build to
+    /// compare against, so a fresh checkout doesn't announce everything.
+    pub async fn notify(
+        &self,
+        tournaments: &[Value],
+        previous_payload: Option<&Value>,
+        config: &Config,
+    ) {
+        let Some(since) = previous_payload
+            .and_then(|payload| payload["generatedAt"].as_str())
+            .and_then(|generated_at| DateTime::parse_from_rfc3339(generated_at).ok())
+            .map(|generated_at| generated_at.with_timezone(&Utc))
+        else {
+            log_warn(
+                "discord",
+                "no previous /api/v1/tournaments.json to compare against; not posting",
+            );
+            return;
+        };
+        let previous_tournaments = previous_payload
+            .and_then(|payload| payload["tournaments"].as_array().cloned())
+            .unwrap_or_default();
+
+        let mut posts = Vec::new();
+        for tournament_data in tournaments {
+            posts.extend(change_posts(tournament_data, &previous_tournaments));
+            posts.extend(notification_posts(
+                tournament_data,
+                config,
+                since,
+                Utc::now(),
+            ));
+        }
+        if posts.is_empty() {
+            log_skip(
+                "discord",
+                &format!("nothing to post since {}", since.to_rfc3339()),
+            );
+            return;
+        }
+
+        let (mut posted, mut failed) = (0, 0);
+        for webhook in &self.webhooks {
+            for post in &posts {
+                let wanted = match post.kind {
+                    PostKind::Notification => webhook.notifications,
+                    PostKind::Change => webhook.changes,
+                };
+                if !wanted {
+                    continue;
+                }
+                match self.post(&webhook.url, &post.body).await {
+                    Ok(()) => {
+                        posted += 1;
+                        log_success(
+                            "discord",
+                            &format!("{}: posted {}", webhook.name, post.label),
+                        );
+                    }
+                    Err(e) => {
+                        failed += 1;
+                        log_error(
+                            "discord",
+                            &format!("{}: {} failed", webhook.name, post.label),
+                        );
+                        log_red(&format!("{e:#}"));
+                    }
+                }
+            }
+        }
+        log_success("discord", &format!("{posted} posted, {failed} failed"));
+    }
+
+    /// Executes the webhook, waiting out Discord's rate limit if it's hit.
+    async fn post(&self, url: &Url, body: &Value) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            // The URL has the webhook's token in it, so keep it out of the logs
+            let response = self
+                .client
+                .post(url.clone())
+                .json(body)
+                .send()
+                .await
+                .map_err(|e| e.without_url())?;
+            let status = response.status();
+            if status.is_success() {
+                return Ok(());
+            }
+            if status == StatusCode::TOO_MANY_REQUESTS && attempt < MAX_ATTEMPTS {
+                let retry_after = response
+                    .json::<Value>()
+                    .await
+                    .ok()
+                    .and_then(|body| body["retry_after"].as_f64())
+                    .unwrap_or(1.0);
+                log_warn(
+                    "discord",
+                    &format!("rate limited, retrying in {retry_after}s"),
+                );
+                sleep(Duration::from_secs_f64(retry_after)).await;
+                attempt += 1;
+                continue;
+            }
+            let body = response.text().await.unwrap_or_default();
+            bail!("response code {}: {body}", status.as_str());
+        }
+    }
+}
+
+/// A tournament's notifications (see `NotificationPolicy`) whose send time is
+/// after `since`, up to `now`.
+fn notification_posts(
+    tournament_data: &Value,
+    config: &Config,
+    since: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Vec<Post> {
+    let tournament_name = tournament_data["name"]
+        .as_str()
+        .unwrap_or("unknown tournament");
+    let policy = match config.notifications.for_tournament(tournament_data) {
+        Ok(policy) => policy,
+        Err(e) => {
+            log_error(
+                "discord",
+                &format!("notifications for {tournament_name} failed"),
+            );
+            log_red(&format!("{e:#}"));
+            return Vec::new();
+        }
+    };
+
+    let mut posts = Vec::new();
+    for kind in BroadcastKind::ALL {
+        let kind_policy = kind.policy(&policy);
+        if !kind_policy.enabled {
+            continue;
+        }
+        for offset in &kind_policy.offsets {
+            match plan_notification(tournament_data, kind, *offset, config) {
+                Ok(PlannedNotification::At(notification))
+                    if since < notification.send_at && notification.send_at <= now =>
+                {
+                    posts.push(Post {
+                        label: notification.label,
+                        kind: PostKind::Notification,
+                        body: message(tournament_data, &notification.intro_text),
+                    });
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log_error("discord", &format!("{tournament_name} failed"));
+                    log_red(&format!("{e:#}"));
+                }
+            }
+        }
+    }
+    posts
+}
+
+/// A notice if the tournament wasn't in the previous build, or if any of its
+/// featured players weren't.
+fn change_posts(tournament_data: &Value, previous_tournaments: &[Value]) -> Vec<Post> {
+    let tournament_name = tournament_data["name"]
+        .as_str()
+        .unwrap_or("unknown tournament");
+    let slug = &tournament_data["start.gg-tournament-name"];
+    let Some(previous) = previous_tournaments.iter().find(|t| t["slug"] == *slug) else {
+        return vec![Post {
+            label: format!("new tournament {tournament_name}"),
+            kind: PostKind::Change,
+            body: message(tournament_data, "New major:"),
+        }];
+    };
+
+    let previous_players = previous["players"].as_array().cloned().unwrap_or_default();
+    let new_players = featured_players(tournament_data)
+        .into_iter()
+        .filter(|player| !previous_players.iter().any(|previous| previous == player))
+        .collect_vec();
+    if new_players.is_empty() {
+        return Vec::new();
+    }
+    let players = new_players.join(", ");
+    vec![Post {
+        label: format!("new players for {tournament_name} ({players})"),
+        kind: PostKind::Change,
+        body: message(tournament_data, &format!("Now registered: **{players}**")),
+    }]
+}
+
+fn featured_players(tournament_data: &Value) -> Vec<&str> {
+    (0..8)
+        .filter_map(|i| tournament_data[format!("player{i}")].as_str())
+        .collect()
+}
+
+/// A webhook message with `content` above an embed of the tournament card,
+/// and a link button for each of its bracket, schedule and stream.
+/// https://discord.com/developers/docs/resources/webhook#execute-webhook
+fn message(tournament_data: &Value, content: &str) -> Value {
+    let url = |key: &str| {
+        tournament_data[key]
+            .as_str()
+            .filter(|url| !url.is_empty())
+            .map(absolute_url)
+    };
+    let featured = featured_players(tournament_data).join(", ");
+    let color = tournament_data["image-color"]
+        .as_str()
+        .and_then(|color| u32::from_str_radix(color.trim_start_matches('#'), 16).ok());
+    let location = match url("maps-link") {
+        Some(maps_link) => format!(
+            "[{}]({maps_link})",
+            tournament_data["city-and-state"].as_str().unwrap_or("")
+        ),
+        None => tournament_data["city-and-state"]
+            .as_str()
+            .unwrap_or("")
+            .to_string(),
+    };
+
+    let embed = json!({
+        "title": tournament_data["name"],
+        "url": url("start.gg-url"),
+        "description": format!("{}\n{location}", tournament_data["date"].as_str().unwrap_or("")),
+        "color": color,
+        "image": { "url": format!("https://meleemajors.gg{}", tournament_data["image-url"].as_str().unwrap_or("")) },
+        "fields": [
+            {
+                "name": "Featured",
+                "value": if featured.is_empty() { "TBD".to_string() } else { featured },
+            },
+            {
+                "name": "Attendees",
+                "value": tournament_data["entrants"].as_str().unwrap_or("TBD"),
+                "inline": true,
+            },
+        ],
+    });
+    let buttons = [
+        ("Bracket", "start.gg-url"),
+        ("Schedule", "schedule-url"),
+        ("Stream", "stream-url"),
+    ]
+    .into_iter()
+    .filter_map(|(label, key)| {
+        // Link buttons: type 2 (button), style 5 (link)
+        Some(json!({ "type": 2, "style": 5, "label": label, "url": url(key)? }))
+    })
+    .collect_vec();
+
+    let mut message = json!({
+        "content": content,
+        "embeds": [embed],
+        // Never ping anyone, whatever ends up in tournament names
+        "allowed_mentions": { "parse": [] },
+    });
+    if !buttons.is_empty() {
+        message["components"] = json!([{ "type": 1, "components": buttons }]);
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::Duration;
+
+    use super::*;
+    use crate::api::tournament_to_api;
+
+    fn tournament(slug: &str, name: &str, top8: DateTime<Utc>, players: &[&str]) -> Value {
+        let timezone = chrono_tz::America::Los_Angeles;
+        let mut tournament = json!({
+            "start.gg-tournament-name": slug,
+            "name": name,
+            "date": "October 17 - October 19",
+            "timezone": timezone.name(),
+            "start-unix-timestamp": (top8 - Duration::days(2)).timestamp(),
+            "end-unix-timestamp": (top8 + Duration::hours(6)).timestamp(),
+            "top8-start-time": top8.with_timezone(&timezone).format("%Y-%m-%d %I:%M%P").to_string(),
+            "image-url": format!("/assets/cards/{slug}.webp"),
+            "image-color": "#1d3557",
+            "entrants": "245",
+            "city-and-state": "San Diego, CA",
+            "maps-link": "https://www.google.com/maps/search/?api=1&query=San%20Diego",
+            "start.gg-url": format!("https://www.start.gg/tournament/{slug}/event/melee-singles"),
+            "stream-url": "",
+            "schedule-url": format!("/schedules/{slug}.html"),
+        });
+        for i in 0..8 {
+            tournament[format!("player{i}")] = json!(players.get(i));
+        }
+        tournament
+    }
+
+    fn contents(posts: &[Post]) -> Vec<&str> {
+        posts
+            .iter()
+            .map(|post| post.body["content"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn notifications_are_posted_once_their_send_time_passes() {
+        let config = Config::default();
+        let top8 = DateTime::parse_from_rfc3339("2026-10-19T22:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let genesis = tournament("genesis", "Genesis", top8, &[]);
+        let minute = Duration::minutes(1);
+
+        assert_eq!(
+            contents(&notification_posts(&genesis, &config, top8 - minute, top8)),
+            ["Top 8 starting now:"]
+        );
+        // After the previous build, up to and including this one
+        assert!(notification_posts(&genesis, &config, top8, top8 + minute).is_empty());
+        assert!(notification_posts(&genesis, &config, top8 - minute * 2, top8 - minute).is_empty());
+        // The 5 day reminder
+        let reminder = top8 - Duration::days(8);
+        assert_eq!(
+            contents(&notification_posts(
+                &genesis,
+                &config,
+                reminder,
+                top8 - minute
+            )),
+            ["This weekend:"]
+        );
+    }
+
+    #[test]
+    fn changes_are_new_tournaments_and_newly_featured_players() {
+        let top8 = Utc::now() + Duration::days(30);
+        let previous = [json!(tournament_to_api(&tournament(
+            "genesis",
+            "Genesis",
+            top8,
+            &["Zain"],
+        )))];
+
+        let unchanged = tournament("genesis", "Genesis", top8, &["Zain"]);
+        assert!(change_posts(&unchanged, &previous).is_empty());
+        let registered = tournament("genesis", "Genesis", top8, &["Zain", "Cody Schwab"]);
+        assert_eq!(
+            contents(&change_posts(&registered, &previous)),
+            ["Now registered: **Cody Schwab**"]
+        );
+        let new = tournament("sapf", "SAPF", top8, &[]);
+        assert_eq!(contents(&change_posts(&new, &previous)), ["New major:"]);
+    }
+
+    /// The URL and JSON body of each request the webhook received
+    type Received = Arc<Mutex<Vec<(String, Value)>>>;
+
+    /// Answers the first request with a rate limit and the rest with 200,
+    /// keeping every request's URL and JSON body.
+    fn webhook_stand_in(requests: usize) -> (String, Received) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api/webhooks/1/token", server.server_addr());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let received_by_server = received.clone();
+        std::thread::spawn(move || {
+            for i in 0..requests {
+                let mut request = server.recv().unwrap();
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                received_by_server.lock().unwrap().push((
+                    request.url().to_string(),
+                    serde_json::from_str(&body).unwrap(),
+                ));
+                let response = if i == 0 {
+                    tiny_http::Response::from_string(r#"{"retry_after": 0.01}"#)
+                        .with_status_code(429)
+                } else {
+                    tiny_http::Response::from_string("{}")
+                };
+                request.respond(response).unwrap();
+            }
+        });
+        (url, received)
+    }
+
+    #[tokio::test]
+    async fn notify_posts_embeds_to_the_webhook() {
+        let (url, received) = webhook_stand_in(4);
+        let config: Config = serde_json::from_value(json!({
+            "discord": {
+                "enabled": true,
+                "webhooks": [{ "name": "test", "url-env": "DISCORD_WEBHOOK_TEST" }],
+            },
+        }))
+        .unwrap();
+        let notifier = DiscordNotifier::from_config_with(&config, |name| {
+            (name == "DISCORD_WEBHOOK_TEST").then(|| url.clone())
+        })
+        .unwrap();
+
+        let now = Utc::now();
+        let previous_payload = json!({
+            "generatedAt": (now - Duration::hours(1)).to_rfc3339(),
+            "tournaments": [tournament_to_api(&tournament(
+                "genesis",
+                "Genesis",
+                now,
+                &["Zain"],
+            ))],
+        });
+        let tournaments = [
+            // Top 8 started since the previous build, and Cody registered
+            tournament(
+                "genesis",
+                "Genesis <@everyone>",
+                now - Duration::minutes(30),
+                &["Zain", "Cody Schwab"],
+            ),
+            tournament("sapf", "SAPF", now + Duration::days(30), &[]),
+        ];
+        notifier
+            .notify(&tournaments, Some(&previous_payload), &config)
+            .await;
+
+        let received = received.lock().unwrap();
+        // The rate limited post is sent again
+        assert_eq!(received.len(), 4);
+        assert_eq!(received[0], received[1]);
+        for (url, _) in received.iter() {
+            assert_eq!(url, "/api/webhooks/1/token?wait=true&with_components=true");
+        }
+        let contents: Vec<&str> = received[1..]
+            .iter()
+            .map(|(_, body)| body["content"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            contents,
+            [
+                "Now registered: **Cody Schwab**",
+                "Top 8 starting now:",
+                "New major:"
+            ]
+        );
+
+        let body = &received[1].1;
+        assert_eq!(body["allowed_mentions"], json!({ "parse": [] }));
+        let embed = &body["embeds"][0];
+        assert_eq!(embed["title"], "Genesis <@everyone>");
+        assert_eq!(
+            embed["url"],
+            "https://www.start.gg/tournament/genesis/event/melee-singles"
+        );
+        assert_eq!(embed["color"], 0x1d3557);
+        assert_eq!(
+            embed["image"]["url"],
+            "https://meleemajors.gg/assets/cards/genesis.webp"
+        );
+        assert_eq!(embed["fields"][0]["value"], "Zain, Cody Schwab");
+        // No stream, so only two buttons
+        assert_eq!(
+            body["components"],
+            json!([{
+                "type": 1,
+                "components": [
+                    {
+                        "type": 2,
+                        "style": 5,
+                        "label": "Bracket",
+                        "url": "https://www.start.gg/tournament/genesis/event/melee-singles",
+                    },
+                    {
+                        "type": 2,
+                        "style": 5,
+                        "label": "Schedule",
+                        "url": "https://meleemajors.gg/schedules/genesis.html",
+                    },
+                ],
+            }])
+        );
+    }
+}
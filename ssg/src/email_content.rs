@@ -0,0 +1,272 @@
+use std::borrow::Cow;
+
+use anyhow::{bail, Result};
+use lol_html::html_content::Element;
+use lol_html::{element, rewrite_str, ElementContentHandlers, RewriteStrSettings, Selector};
+use regex::Regex;
+use reqwest::Url;
+use scraper::Html;
+use serde_json::Value;
+
+use crate::utils::replace_placeholder_values;
+
+/// Gmail hides everything past this behind a "view entire message" link.
+const MAX_HTML_BYTES: usize = 102 * 1024;
+
+/// Temporarily holds the declarations inlined into an element until they're
+/// merged with its own `style`.
+const INLINED_STYLE_ATTRIBUTE: &str = "data-inlined-style";
+
+/// Renders an HTML email template with `data`.
+///
+/// - Rules in `<style>` blocks are copied into the `style` attribute of every
+///   element they match, since a lot of email clients ignore `<style>`. They're
+///   applied in the order they're written (specificity isn't considered), and
+///   anything already in `style` still wins. At-rules like `@media` are kept in
+///   a `<style>` block for the clients that do support it.
+/// - Elements with `data-if="key"` are removed when `data[key]` is null or empty.
+/// - Comments are removed, and `{{key}}` placeholders are filled in.
+pub fn render_html(template: &str, data: &Value) -> Result<String> {
+    // Comments first, since they can mention <style>
+    let template = Regex::new(r"(?s)<!--.*?-->\s*")
+        .unwrap()
+        .replace_all(template, "");
+    let style_blocks = Regex::new(r"(?s)<style[^>]*>(.*?)</style>\s*").unwrap();
+    let css: String = style_blocks
+        .captures_iter(&template)
+        .map(|captures| captures[1].to_string())
+        .collect();
+    let template = style_blocks.replace_all(&template, "");
+    let (rules, at_rules) = parse_css(&css);
+
+    let mut handlers = vec![element!("[data-if]", |el| {
+        let key = el.get_attribute("data-if").unwrap_or_default();
+        match &data[&key] {
+            Value::Null => el.remove(),
+            Value::String(value) if value.is_empty() => el.remove(),
+            _ => el.remove_attribute("data-if"),
+        }
+        Ok(())
+    })];
+    for (selector, declarations) in &rules {
+        let selector: Selector = match selector.parse() {
+            Ok(selector) => selector,
+            Err(e) => bail!("unsupported selector in email template: {selector} ({e})"),
+        };
+        handlers.push((
+            Cow::Owned(selector),
+            ElementContentHandlers::default().element(move |el: &mut Element| {
+                let inlined = el
+                    .get_attribute(INLINED_STYLE_ATTRIBUTE)
+                    .unwrap_or_default();
+                el.set_attribute(INLINED_STYLE_ATTRIBUTE, &(inlined + declarations))?;
+                Ok(())
+            }),
+        ));
+    }
+    // Runs after the rules above, since handlers run in the order they're listed
+    handlers.push(element!("*", |el| {
+        if let Some(inlined) = el.get_attribute(INLINED_STYLE_ATTRIBUTE) {
+            el.remove_attribute(INLINED_STYLE_ATTRIBUTE);
+            let own_style = el.get_attribute("style").unwrap_or_default();
+            el.set_attribute("style", (inlined + own_style.trim()).trim_end())?;
+        }
+        Ok(())
+    }));
+
+    let mut html = rewrite_str(
+        &template,
+        RewriteStrSettings {
+            element_content_handlers: handlers,
+            ..RewriteStrSettings::new()
+        },
+    )?;
+    if !at_rules.is_empty() {
+        html = format!("<style>\n{at_rules}\n</style>\n{html}");
+    }
+    Ok(replace_placeholder_values(data, &html))
+}
+
+/// Renders a plain text email template with `data`, without any runs of blank
+/// lines left by empty values.
+pub fn render_text(template: &str, data: &Value) -> String {
+    let text = replace_placeholder_values(data, template).replace("\r\n", "\n");
+    Regex::new(r"\n{3,}")
+        .unwrap()
+        .replace_all(text.trim(), "\n\n")
+        .to_string()
+}
+
+/// Catches problems that would make an email look broken or be hard to read
+/// with a screen reader, so it isn't scheduled.
+pub fn check_email(html: &str, text: &str) -> Result<()> {
+    let mut problems = Vec::new();
+    if html.len() > MAX_HTML_BYTES {
+        problems.push(format!(
+            "html is {} KB, Gmail clips emails over {} KB",
+            html.len() / 1024,
+            MAX_HTML_BYTES / 1024
+        ));
+    }
+    if text.trim().is_empty() {
+        problems.push("plain text version is empty".to_string());
+    }
+    for (version, content) in [("html", html), ("plain text", text)] {
+        if content.contains("{{") {
+            problems.push(format!("unfilled placeholder in {version} version"));
+        }
+    }
+
+    let document = Html::parse_fragment(html);
+    for image in document.select(&scraper::Selector::parse("img").unwrap()) {
+        let src = image.value().attr("src").unwrap_or_default();
+        if image
+            .value()
+            .attr("alt")
+            .is_none_or(|alt| alt.trim().is_empty())
+        {
+            problems.push(format!("image without alt text: {src}"));
+        }
+        if !is_valid_url(src, &["https"]) {
+            problems.push(format!("image with invalid src: {src:?}"));
+        }
+    }
+    for link in document.select(&scraper::Selector::parse("a").unwrap()) {
+        let href = link.value().attr("href").unwrap_or_default();
+        if !is_valid_url(href, &["http", "https", "mailto"]) {
+            problems.push(format!("link with invalid target: {href:?}"));
+        }
+    }
+
+    if !problems.is_empty() {
+        bail!("email failed checks: {}", problems.join(", "));
+    }
+    Ok(())
+}
+
+/// `(selector, declarations)` for every plain rule, with selector lists split
+/// up, and all the at-rules as they were written.
+fn parse_css(css: &str) -> (Vec<(String, String)>, String) {
+    let css = Regex::new(r"(?s)/\*.*?\*/").unwrap().replace_all(css, "");
+    let mut rules = Vec::new();
+    let mut at_rules = Vec::new();
+    let mut rest = css.trim();
+    while let Some(open) = rest.find('{') {
+        let prelude = rest[..open].trim();
+        // Find the matching brace, since at-rules can contain whole rules
+        let mut depth = 0;
+        let close = rest[open..]
+            .char_indices()
+            .find_map(|(i, c)| {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+                (depth == 0).then_some(open + i)
+            })
+            .unwrap_or(rest.len() - 1);
+        let block = &rest[open + 1..close];
+        if prelude.starts_with('@') {
+            at_rules.push(rest[..=close].trim().to_string());
+        } else {
+            let declarations: String = block
+                .split(';')
+                .map(str::trim)
+                .filter(|declaration| !declaration.is_empty())
+                .map(|declaration| format!("{declaration}; "))
+                .collect();
+            for selector in prelude.split(',') {
+                rules.push((selector.trim().to_string(), declarations.clone()));
+            }
+        }
+        rest = rest[close + 1..].trim_start();
+    }
+    (rules, at_rules.join("\n"))
+}
+
+fn is_valid_url(url: &str, schemes: &[&str]) -> bool {
+    Url::parse(url).is_ok_and(|url| {
+        schemes.contains(&url.scheme()) && (url.scheme() == "mailto" || url.has_host())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const HTML: &str = r#"<p><img src="https://meleemajors.gg/assets/cards/sapf2.webp" alt="SAPF 2 banner"><a href="https://www.start.gg/tournament/sapf-2">Bracket</a> <a href="mailto:hello@meleemajors.gg">Email us</a></p>"#;
+
+    #[test]
+    fn css_rules_are_split_per_selector() {
+        let (rules, at_rules) = parse_css(
+            "/* buttons */ .button, a.link { color: red; padding: 4px }\n\
+             td{border:0;;}\n\
+             @media (max-width: 600px) { .button { width: 100%; } }\n\
+             h2 { margin: 0; }",
+        );
+        assert_eq!(
+            rules,
+            [
+                (".button", "color: red; padding: 4px; "),
+                ("a.link", "color: red; padding: 4px; "),
+                ("td", "border:0; "),
+                ("h2", "margin: 0; "),
+            ]
+            .map(|(selector, declarations)| (selector.to_string(), declarations.to_string()))
+        );
+        assert_eq!(
+            at_rules,
+            "@media (max-width: 600px) { .button { width: 100%; } }"
+        );
+    }
+
+    #[test]
+    fn css_is_inlined_under_existing_styles() {
+        let html = render_html(
+            "<style>.button { color: red; } a { padding: 4px; }</style>\
+             <!-- note --><a class=\"button\" style=\"color: blue\" href=\"{{url}}\">Go</a>\
+             <p data-if=\"stream\">Stream</p>",
+            &json!({ "url": "https://meleemajors.gg", "stream": "" }),
+        )
+        .unwrap();
+        assert_eq!(
+            html,
+            "<a class=\"button\" style=\"color: red; padding: 4px; color: blue\" href=\"https://meleemajors.gg\">Go</a>"
+        );
+    }
+
+    #[test]
+    fn good_email_passes_checks() {
+        check_email(
+            HTML,
+            "SAPF 2\nBracket: https://www.start.gg/tournament/sapf-2",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn check_email_reports_every_problem() {
+        let check = |html: &str, text: &str| check_email(html, text).unwrap_err().to_string();
+        assert!(check(HTML, " \n").contains("plain text version is empty"));
+        assert!(check(HTML, "Hi {{name}}").contains("unfilled placeholder in plain text"));
+        assert!(
+            check(&format!("{HTML}{{{{name}}}}"), "Hi").contains("unfilled placeholder in html")
+        );
+        assert!(check(r#"<img src="https://meleemajors.gg/a.webp">"#, "Hi")
+            .contains("image without alt text"));
+        assert!(
+            check(r#"<img src="/assets/a.webp" alt="a">"#, "Hi").contains("image with invalid src")
+        );
+        assert!(
+            check(r#"<img src="http://meleemajors.gg/a.webp" alt="a">"#, "Hi")
+                .contains("image with invalid src")
+        );
+        assert!(check(r#"<a href="">Schedule</a>"#, "Hi").contains("link with invalid target"));
+        assert!(check(&"a".repeat(MAX_HTML_BYTES + 1), "Hi").contains("Gmail clips"));
+
+        let all = check(r#"<img src=""><a href="TBD">x</a>"#, "");
+        assert_eq!(all.matches(", ").count(), 3, "{all}");
+    }
+}
@@ -1,8 +1,25 @@
 use std::fs;
 
-use crate::read_file;
+use crate::paths::Paths;
+use crate::utils::log_success;
 
-pub fn main() {
+/// Where the generated query is written, relative to the template directory.
+pub const FEATURED_PLAYERS_QUERY_PATH: &str = "graphql/getFeaturedPlayers.gql";
+
+/// Regenerates getFeaturedPlayers.gql from the players in `top_players_path`.
+pub fn main(top_players_path: &str, paths: &Paths) {
+    let top_players = fs::read_to_string(top_players_path)
+        .unwrap_or_else(|e| panic!("failed to read {top_players_path}: {e}"));
+    fs::write(
+        paths.template(FEATURED_PLAYERS_QUERY_PATH),
+        featured_players_query(&top_players),
+    )
+    .unwrap();
+    log_success("gql", &format!("wrote {FEATURED_PLAYERS_QUERY_PATH}"));
+}
+
+/// One aliased entrant search per player in topPlayers.json, all in one query.
+pub fn featured_players_query(top_players: &str) -> String {
     let mut get_featured_players_query =
         "query getFeaturedPlayers($slug_event: String!) {".to_owned();
 
@@ -16,8 +33,7 @@ pub fn main() {
     }"#;
     let get_featured_players_footer = "\n}";
 
-    let top_players = read_file("topPlayers.json");
-    serde_json::from_str::<Vec<String>>(&top_players)
+    serde_json::from_str::<Vec<String>>(top_players)
         .unwrap()
         .iter()
         .enumerate()
@@ -29,10 +45,5 @@ pub fn main() {
         });
 
     get_featured_players_query.push_str(get_featured_players_footer);
-
-    fs::write(
-        "src/graphql/getFeaturedPlayers.gql",
-        get_featured_players_query,
-    )
-    .unwrap();
+    get_featured_players_query
 }
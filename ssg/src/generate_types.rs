@@ -0,0 +1,322 @@
+use std::fs;
+
+use serde_json::Value;
+
+use crate::paths::Paths;
+use crate::utils::log_success;
+
+const HEADER: &str =
+    "@generated by ssg/src/generate_types.rs from tournaments.schema.json. Do not edit by hand.";
+
+/// The Rust types in the templates directory, which are the library's `model`
+/// module.
+const MODEL_PATH: &str = "model/tournaments.rs";
+
+/// Regenerates the library's `model` module, and the client types published
+/// next to tournaments.schema.json.
+pub fn main(paths: &Paths) {
+    let schema = read_schema(paths);
+    fs::write(paths.template(MODEL_PATH), rust_types(&schema)).unwrap();
+    log_success("types", &format!("wrote {MODEL_PATH}"));
+    publish(paths);
+}
+
+/// Writes the TypeScript and Rust client types next to
+/// tournaments.schema.json, so consumers of the API don't have to hand-write them.
+pub fn publish(paths: &Paths) {
+    let schema = read_schema(paths);
+    fs::write(
+        paths.site("api/v1/tournaments.d.ts"),
+        typescript_types(&schema),
+    )
+    .unwrap();
+    log_success("types", "wrote /api/v1/tournaments.d.ts");
+
+    fs::write(paths.site("api/v1/tournaments.rs"), rust_types(&schema)).unwrap();
+    log_success("types", "wrote /api/v1/tournaments.rs");
+}
+
+fn read_schema(paths: &Paths) -> Value {
+    let schema_path = paths.static_file("api/v1/tournaments.schema.json");
+    serde_json::from_str(&fs::read_to_string(&schema_path).unwrap())
+        .expect("tournaments.schema.json is not valid JSON")
+}
+
+/// The root of the schema becomes `TournamentsResponse`, and every entry in
+/// `$defs` becomes a type named after its key.
+fn schema_types(schema: &Value) -> Vec<(String, &Value)> {
+    let mut types = vec![("TournamentsResponse".to_string(), schema)];
+    if let Some(defs) = schema["$defs"].as_object() {
+        for (name, def) in defs {
+            types.push((pascal_case(name), def));
+        }
+    }
+    types
+}
+
+fn typescript_types(schema: &Value) -> String {
+    let mut out = format!("// {HEADER}\n");
+    for (name, def) in schema_types(schema) {
+        out.push('\n');
+        out.push_str(&typescript_doc(def, ""));
+        out.push_str(&format!("export interface {name} {{\n"));
+        for (key, prop, required) in properties(def) {
+            out.push_str(&typescript_doc(prop, "  "));
+            let optional = if required { "" } else { "?" };
+            out.push_str(&format!("  {key}{optional}: {};\n", typescript_type(prop)));
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+fn rust_types(schema: &Value) -> String {
+    let mut out = format!("// {HEADER}\n\nuse serde::{{Deserialize, Serialize}};\n");
+    for (name, def) in schema_types(schema) {
+        out.push('\n');
+        out.push_str(&rust_doc(def, ""));
+        out.push_str("#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n");
+        out.push_str(&format!("pub struct {name} {{\n"));
+        for (key, prop, required) in properties(def) {
+            out.push_str(&rust_doc(prop, "    "));
+            let field = snake_case(key);
+            if prop["deprecated"].as_bool() == Some(true) {
+                out.push_str("    #[deprecated]\n");
+            }
+            if field != key {
+                out.push_str(&format!("    #[serde(rename = \"{key}\")]\n"));
+            }
+            let mut field_type = rust_type(prop);
+            if !required {
+                out.push_str("    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n");
+                if !field_type.starts_with("Option<") {
+                    field_type = format!("Option<{field_type}>");
+                }
+            }
+            out.push_str(&format!("    pub {field}: {field_type},\n"));
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+/// `(name, schema, required)` for each property of an object schema, in schema order.
+fn properties(def: &Value) -> Vec<(&str, &Value, bool)> {
+    let required: Vec<&str> = def["required"]
+        .as_array()
+        .map(|keys| keys.iter().filter_map(|key| key.as_str()).collect())
+        .unwrap_or_default();
+    let Some(props) = def["properties"].as_object() else {
+        return Vec::new();
+    };
+    props
+        .iter()
+        .map(|(key, prop)| (key.as_str(), prop, required.contains(&key.as_str())))
+        .collect()
+}
+
+/// Splits `"type": ["string", "null"]` into `("string", true)`.
+fn base_type(prop: &Value) -> (&str, bool) {
+    match &prop["type"] {
+        Value::String(t) => (t.as_str(), false),
+        Value::Array(types) => {
+            let nullable = types.iter().any(|t| t == "null");
+            let t = types
+                .iter()
+                .filter_map(|t| t.as_str())
+                .find(|t| *t != "null")
+                .unwrap_or("null");
+            (t, nullable)
+        }
+        _ => ("", false),
+    }
+}
+
+fn ref_name(prop: &Value) -> Option<String> {
+    prop["$ref"]
+        .as_str()
+        .and_then(|r| r.strip_prefix("#/$defs/"))
+        .map(pascal_case)
+}
+
+fn typescript_type(prop: &Value) -> String {
+    if let Some(name) = ref_name(prop) {
+        return name;
+    }
+    let (t, nullable) = base_type(prop);
+    let ts = match t {
+        "string" if prop["enum"].is_array() => prop["enum"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>()
+            .join(" | "),
+        "string" => "string".to_string(),
+        "integer" | "number" => "number".to_string(),
+        "boolean" => "boolean".to_string(),
+        "array" => {
+            let item = typescript_type(&prop["items"]);
+            if item.contains(' ') {
+                format!("({item})[]")
+            } else {
+                format!("{item}[]")
+            }
+        }
+        _ => "unknown".to_string(),
+    };
+    if nullable {
+        format!("{ts} | null")
+    } else {
+        ts
+    }
+}
+
+fn rust_type(prop: &Value) -> String {
+    if let Some(name) = ref_name(prop) {
+        return name;
+    }
+    let (t, nullable) = base_type(prop);
+    let rust = match t {
+        "string" => "String".to_string(),
+        "integer" if prop["minimum"].as_i64().is_some_and(|min| min >= 0) => "u64".to_string(),
+        "integer" => "i64".to_string(),
+        "number" => "f64".to_string(),
+        "boolean" => "bool".to_string(),
+        "array" => format!("Vec<{}>", rust_type(&prop["items"])),
+        _ => "serde_json::Value".to_string(),
+    };
+    if nullable {
+        format!("Option<{rust}>")
+    } else {
+        rust
+    }
+}
+
+fn typescript_doc(def: &Value, indent: &str) -> String {
+    let mut lines: Vec<String> = description_lines(def);
+    if def["deprecated"].as_bool() == Some(true) {
+        lines.push("@deprecated".to_string());
+    }
+    match lines.len() {
+        0 => String::new(),
+        1 => format!("{indent}/** {} */\n", lines[0]),
+        _ => {
+            let mut doc = format!("{indent}/**\n");
+            for line in lines {
+                doc.push_str(&format!("{indent} * {line}\n"));
+            }
+            doc.push_str(&format!("{indent} */\n"));
+            doc
+        }
+    }
+}
+
+fn rust_doc(def: &Value, indent: &str) -> String {
+    description_lines(def)
+        .iter()
+        .map(|line| format!("{indent}/// {line}\n"))
+        .collect()
+}
+
+fn description_lines(def: &Value) -> Vec<String> {
+    def["description"]
+        .as_str()
+        .map(|d| d.lines().map(|line| line.replace("*/", "*\\/")).collect())
+        .unwrap_or_default()
+}
+
+fn pascal_case(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// `startggUrl` -> `startgg_url`, `top8StartTime` -> `top8_start_time`, `$schema` -> `schema`
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.trim_start_matches('$').chars() {
+        if c.is_uppercase() {
+            if !out.is_empty() {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::model;
+
+    #[test]
+    fn generated_rust_types_round_trip_api_payload() {
+        let tournament = json!({
+            "start.gg-tournament-name": "sapf2",
+            "image-url": "/assets/cards/sapf2.1abf78a10ecf.webp",
+            "image-url-thumbnail": null,
+            "image-color": "#1d3557",
+            "image-placeholder": "data:image/webp;base64,UklGRhoAAABXRUJQVlA4TA0AAAAvAAAAEAcQERGIiP4HAA==",
+            "name": "SAPF 2",
+            "date": "April 10 - April 12",
+            "start-unix-timestamp": 1775826000,
+            "end-unix-timestamp": 1776034800,
+            "timezone": "America/Los_Angeles",
+            "player0": "Zain",
+            "player1": "Cody Schwab",
+            "player2": null,
+            "player3": null,
+            "player4": null,
+            "player5": null,
+            "player6": null,
+            "player7": null,
+            "entrants": "245",
+            "city-and-state": "San Diego, CA",
+            "maps-link": "https://www.google.com/maps/search/?api=1&query=Price%20Center",
+            "full-address": "Price Center, La Jolla, CA 92093, USA",
+            "start.gg-url": "https://www.start.gg/tournament/sapf-2/event/melee-singles",
+            "stream-url": "https://www.twitch.tv/sapf",
+            "schedule-url": "",
+            "schedule-link-class": " hidden",
+            "stream-link-class": "",
+            "top8-start-time": null,
+        });
+        let payload =
+            serde_json::to_value(crate::api::api_payload(&[tournament], &chrono::Utc::now()))
+                .unwrap();
+
+        let parsed: model::TournamentsResponse =
+            serde_json::from_value(payload.clone()).expect("payload should deserialize");
+        let round_tripped = serde_json::to_value(&parsed).unwrap();
+
+        assert_eq!(round_tripped, payload);
+    }
+
+    /// Fails when tournaments.schema.json changed without running
+    /// `cargo run -- types generate`.
+    #[test]
+    fn checked_in_types_match_schema() {
+        let read = |file: &str| {
+            std::fs::read_to_string(format!("{}/{file}", env!("CARGO_MANIFEST_DIR"))).unwrap()
+        };
+        let schema = serde_json::from_str(&read("../site/api/v1/tournaments.schema.json")).unwrap();
+
+        assert_eq!(
+            read("../site/api/v1/tournaments.d.ts"),
+            super::typescript_types(&schema),
+            "tournaments.d.ts is out of date, run `cargo run -- types generate`"
+        );
+        assert_eq!(
+            read("src/model/tournaments.rs"),
+            super::rust_types(&schema),
+            "model/tournaments.rs is out of date, run `cargo run -- types generate`"
+        );
+    }
+}
@@ -0,0 +1,796 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use image::codecs::avif::AvifEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageEncoder};
+use itertools::Itertools;
+use reqwest::{header, StatusCode};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::paths::Paths;
+use crate::utils::{log_skip, log_success, log_warn};
+
+/// Widest cards render images at, in CSS pixels
+const CARD_IMAGE_WIDTH: u32 = 330;
+
+/// Height cards render images at, in CSS pixels
+const CARD_IMAGE_HEIGHT: u32 = 170;
+
+/// Width / height of the image area on a card
+pub const CARD_ASPECT_RATIO: f64 = CARD_IMAGE_WIDTH as f64 / CARD_IMAGE_HEIGHT as f64;
+
+/// Pixel densities to generate variants for. Densities above 2x are skipped
+/// when the source image isn't tall enough, rather than upscaling.
+const DENSITIES: [u32; 3] = [1, 2, 3];
+
+/// The 2x WebP is also saved as `{name}.{hash}.webp`, the card's `image-url`, which
+/// the API, emails, Discord and social posts link to. The hash changes with the
+/// image, so a changed image gets a new URL rather than a stale cached one
+const CANONICAL_DENSITY: u32 = 2;
+
+/// Lossy WebP quality, matching libwebp's (and ffmpeg's) default
+const WEBP_QUALITY: f32 = 75.0;
+
+const AVIF_QUALITY: u8 = 70;
+
+/// 1 (slowest, smallest) to 10 (fastest)
+const AVIF_SPEED: u8 = 6;
+
+/// Width of the blurred placeholder inlined into the page while the real image loads
+const PLACEHOLDER_WIDTH: u32 = 20;
+
+/// Recorded in the manifest and mixed into file hashes, so changing any of the
+/// encoder settings above gives every image new file names, e.g.
+/// `webp-q75;avif-q70-s6;densities-1,2,3;height-170;lqip-20`
+fn encoding() -> String {
+    format!(
+        "webp-q{WEBP_QUALITY};avif-q{AVIF_QUALITY}-s{AVIF_SPEED};densities-{};height-{CARD_IMAGE_HEIGHT};lqip-{PLACEHOLDER_WIDTH}",
+        DENSITIES.iter().join(",")
+    )
+}
+
+/// Shown behind the default card image, and for any image without a usable color
+const FALLBACK_COLOR: &str = "#181a1b";
+
+/// Length of the content hash in file names, in hex characters
+const HASH_LENGTH: usize = 12;
+
+/// Lives next to the images it describes, so caching `cards/` caches both
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// How long to wait for a banner to download before giving up on it, so one
+/// stalled host can't hang the build
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Schedule images wider than this are scaled down. Most are screenshots or
+/// posters meant to be read on a phone, so this keeps text legible.
+const SCHEDULE_MAX_WIDTH: u32 = 1600;
+
+/// Site-relative URL of the image used when a tournament's banner can't be downloaded
+pub const DEFAULT_CARD_IMAGE_URL: &str = "/assets/defaultCard.webp";
+
+/// A processed image and all of its responsive variants.
+pub struct CardImage {
+    /// Site-relative URL of the canonical WebP
+    pub url: String,
+    /// Size of the 1x variant, i.e. in CSS pixels
+    pub width: u32,
+    pub height: u32,
+    pub srcset_webp: String,
+    pub srcset_avif: String,
+    /// Most common color, as `#rrggbb`
+    pub color: String,
+    /// Tiny blurred WebP as a `data:` URI
+    pub placeholder: String,
+    /// Every file in `cards/` belonging to this image
+    pub files: Vec<String>,
+}
+
+impl CardImage {
+    /// The default card image shipped in `site/assets`
+    pub fn default_card(paths: &Paths) -> Self {
+        let (color, placeholder) = image::open(paths.static_file("assets/defaultCard.webp"))
+            .ok()
+            .and_then(|image| Some((dominant_color(&image), placeholder(&image).ok()?)))
+            .unwrap_or_else(|| (FALLBACK_COLOR.to_string(), String::new()));
+        Self {
+            url: DEFAULT_CARD_IMAGE_URL.to_string(),
+            width: CARD_IMAGE_WIDTH,
+            height: CARD_IMAGE_HEIGHT,
+            srcset_webp: format!("{DEFAULT_CARD_IMAGE_URL} 2x"),
+            srcset_avif: "/assets/defaultCard.avif 2x".to_string(),
+            color,
+            placeholder,
+            files: Vec::new(),
+        }
+    }
+}
+
+/// The images in `cards/` from previous builds, keyed by image name, along with
+/// the start.gg URL, ETag and content hash each one was generated from.
+///
+/// Stored as `cards/manifest.json`. File names include the content hash, so a
+/// changed banner gets new URLs and stale copies can't be served from a cache.
+pub struct ImageCache {
+    /// `cards/` in the cache directory
+    dir: String,
+    entries: Map<String, Value>,
+    used: HashSet<String>,
+    /// Reuse cached remote images without revalidating them, for rebuilds
+    /// that don't go online
+    offline: bool,
+    /// Shared by every download, so connections to start.gg's CDN are reused
+    client: reqwest::Client,
+}
+
+impl ImageCache {
+    pub fn load(paths: &Paths, offline: bool) -> Self {
+        let dir = paths.cache("cards");
+        let path = format!("{dir}/{MANIFEST_FILE}");
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str::<Value>(&contents)
+                .ok()
+                .and_then(|manifest| manifest["images"].as_object().cloned())
+                .unwrap_or_else(|| {
+                    log_warn("image", &format!("ignoring unreadable {path}"));
+                    Map::new()
+                }),
+            Err(_) => Map::new(),
+        };
+        Self {
+            dir,
+            entries,
+            used: HashSet::new(),
+            offline,
+            client: reqwest::Client::builder()
+                .timeout(DOWNLOAD_TIMEOUT)
+                .build()
+                .unwrap(),
+        }
+    }
+
+    /// Writes the manifest, dropping images that weren't used by this build.
+    pub fn save(&self) -> Result<()> {
+        let images: Map<String, Value> = self
+            .entries
+            .iter()
+            .filter(|(name, _)| self.used.contains(*name))
+            .map(|(name, entry)| (name.clone(), entry.clone()))
+            .collect();
+        let path = format!("{}/{MANIFEST_FILE}", self.dir);
+        fs::create_dir_all(&self.dir)?;
+        fs::write(
+            &path,
+            serde_json::to_string_pretty(&json!({ "images": images }))?,
+        )
+        .with_context(|| format!("failed to write {path}"))
+    }
+
+    /// The cached entry for `name`, if it came from `source` with the current
+    /// hints and encoder settings, and all of its files are still on disk.
+    fn entry(&self, name: &str, source: &str, hints: &str) -> Option<&Value> {
+        let entry = self.entries.get(name)?;
+        let valid = entry["sourceUrl"] == source
+            && entry["hints"].as_str().unwrap_or_default() == hints
+            && entry["encoding"] == encoding()
+            && entry["files"].as_array().is_some_and(|files| {
+                files.iter().all(|file| {
+                    file.as_str()
+                        .is_some_and(|file| fs::metadata(format!("{}/{file}", self.dir)).is_ok())
+                })
+            });
+        valid.then_some(entry)
+    }
+
+    /// Marks `name` as used by this build and rebuilds its `CardImage` from `entry`.
+    fn reuse(&mut self, name: &str, entry: Value) -> CardImage {
+        let densities: Vec<u32> = entry["densities"]
+            .as_array()
+            .map(|densities| {
+                densities
+                    .iter()
+                    .filter_map(|density| density.as_u64())
+                    .map(|density| density as u32)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let files = entry["files"]
+            .as_array()
+            .map(|files| {
+                files
+                    .iter()
+                    .filter_map(|file| file.as_str())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let card_image = CardImage {
+            color: entry["color"]
+                .as_str()
+                .unwrap_or(FALLBACK_COLOR)
+                .to_string(),
+            placeholder: entry["placeholder"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            ..card_image(
+                &format!("{name}.{}", entry["hash"].as_str().unwrap_or_default()),
+                entry["width"].as_u64().unwrap_or_default() as u32,
+                entry["height"].as_u64().unwrap_or_default() as u32,
+                &densities,
+                files,
+            )
+        };
+        self.entries.insert(name.to_string(), entry);
+        self.used.insert(name.to_string());
+        card_image
+    }
+}
+
+/// Where an image comes from: start.gg (or any other remote URL), or a file
+/// committed under `site/assets`.
+pub enum ImageSource {
+    Remote(String),
+    /// Path relative to `site`, e.g. `assets/banners/riptide-2026.png`
+    Local(String),
+}
+
+impl ImageSource {
+    /// Parses an `image-url`/`thumbnail-url` override from tournaments.json.
+    /// Like `schedule-url`, local paths are relative to `site`.
+    pub fn from_override(value: &str) -> Result<Self> {
+        if value.starts_with("http://") || value.starts_with("https://") {
+            return Ok(Self::Remote(value.to_string()));
+        }
+        let path = value.trim_start_matches('/');
+        let path = path.strip_prefix("site/").unwrap_or(path);
+        if !path.starts_with("assets/") || path.split('/').any(|part| part == "..") {
+            bail!("{value:?} is neither a URL nor a path under site/assets");
+        }
+        Ok(Self::Local(path.to_string()))
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Remote(url) | Self::Local(url) => url,
+        }
+    }
+}
+
+/// Manual crop/focal-point hints for images start.gg crops badly.
+pub struct ImageHints {
+    /// `(x, y, width, height)` in source pixels, applied before anything else
+    crop: Option<(u32, u32, u32, u32)>,
+    /// `(x, y)` as fractions of the (cropped) image. When set, the image is cut
+    /// down to `aspect_ratio` around this point instead of being center-cropped
+    /// by the browser.
+    focal_point: Option<(f64, f64)>,
+    aspect_ratio: f64,
+}
+
+impl ImageHints {
+    /// Reads `{prefix}-crop` and `{prefix}-focal-point` from a tournaments.json entry, e.g.
+    /// `"image-crop": { "x": 0, "y": 40, "width": 1200, "height": 620 }` and
+    /// `"image-focal-point": { "x": 0.5, "y": 0.3 }`.
+    pub fn from_overrides(tournament: &Value, prefix: &str, aspect_ratio: f64) -> Result<Self> {
+        let crop = match &tournament[format!("{prefix}-crop")] {
+            Value::Null => None,
+            crop => {
+                let field = |key: &str| {
+                    crop[key]
+                        .as_u64()
+                        .map(|value| value as u32)
+                        .with_context(|| format!("{prefix}-crop.{key} must be a whole number"))
+                };
+                let (width, height) = (field("width")?, field("height")?);
+                if width == 0 || height == 0 {
+                    bail!("{prefix}-crop must not be empty");
+                }
+                Some((field("x")?, field("y")?, width, height))
+            }
+        };
+        let focal_point = match &tournament[format!("{prefix}-focal-point")] {
+            Value::Null => None,
+            point => {
+                let field = |key: &str| {
+                    point[key]
+                        .as_f64()
+                        .filter(|value| (0.0..=1.0).contains(value))
+                        .with_context(|| {
+                            format!("{prefix}-focal-point.{key} must be between 0 and 1")
+                        })
+                };
+                Some((field("x")?, field("y")?))
+            }
+        };
+        Ok(Self {
+            crop,
+            focal_point,
+            aspect_ratio,
+        })
+    }
+
+    /// Recorded in the manifest and mixed into file hashes, like [`encoding`]
+    fn key(&self) -> String {
+        let mut key = String::new();
+        if let Some((x, y, width, height)) = self.crop {
+            key.push_str(&format!("crop-{x},{y},{width},{height};"));
+        }
+        if let Some((x, y)) = self.focal_point {
+            key.push_str(&format!("focal-{x},{y},{:.4}", self.aspect_ratio));
+        }
+        key
+    }
+
+    fn apply(&self, image: DynamicImage) -> Result<DynamicImage> {
+        let mut image = image;
+        if let Some((x, y, width, height)) = self.crop {
+            if x.saturating_add(width) > image.width() || y.saturating_add(height) > image.height()
+            {
+                bail!(
+                    "crop {width}x{height}+{x}+{y} is outside the {}x{} image",
+                    image.width(),
+                    image.height()
+                );
+            }
+            image = image.crop_imm(x, y, width, height);
+        }
+        if let Some((focal_x, focal_y)) = self.focal_point {
+            let (image_width, image_height) = (image.width() as f64, image.height() as f64);
+            let (width, height) = if image_width / image_height > self.aspect_ratio {
+                (image_height * self.aspect_ratio, image_height)
+            } else {
+                (image_width, image_width / self.aspect_ratio)
+            };
+            let x = (focal_x * image_width - width / 2.0).clamp(0.0, image_width - width);
+            let y = (focal_y * image_height - height / 2.0).clamp(0.0, image_height - height);
+            image = image.crop_imm(
+                x.round() as u32,
+                y.round() as u32,
+                (width.round() as u32).max(1),
+                (height.round() as u32).max(1),
+            );
+        }
+        Ok(image)
+    }
+}
+
+/// Fetches `source` and saves `cards/{name}.{hash}.webp` plus WebP and AVIF
+/// variants for each pixel density.
+///
+/// Images already in `cache` are revalidated (with their ETag, for remote
+/// images), and only re-encoded when the content or `hints` change.
+pub async fn download_tournament_image(
+    source: &ImageSource,
+    name: &str,
+    hints: &ImageHints,
+    cache: &mut ImageCache,
+    paths: &Paths,
+) -> Result<CardImage> {
+    fs::create_dir_all(&cache.dir)?;
+    let cached = cache.entry(name, source.as_str(), &hints.key()).cloned();
+
+    let (bytes, etag) = match source {
+        ImageSource::Local(path) => {
+            let bytes = fs::read(paths.static_file(path))
+                .with_context(|| format!("failed to read site/{path}"))?;
+            (bytes, None)
+        }
+        ImageSource::Remote(url) => {
+            if cache.offline {
+                return match cached {
+                    Some(entry) => Ok(cache.reuse(name, entry)),
+                    None => bail!("{url} isn't cached, run `cargo run -- build` first"),
+                };
+            }
+            let mut request = cache.client.get(url);
+            if let Some(etag) = cached.as_ref().and_then(|entry| entry["etag"].as_str()) {
+                request = request.header(header::IF_NONE_MATCH, etag);
+            }
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(e) => match cached {
+                    // Keep building with the last known image when start.gg is unreachable
+                    Some(entry) => {
+                        log_warn("image", &format!("using cached {name}, {url} failed: {e}"));
+                        return Ok(cache.reuse(name, entry));
+                    }
+                    None => return Err(e).with_context(|| format!("failed to download {url}")),
+                },
+            };
+
+            if response.status() == StatusCode::NOT_MODIFIED {
+                if let Some(entry) = cached {
+                    log_skip("image", &format!("{name} not modified"));
+                    return Ok(cache.reuse(name, entry));
+                }
+            }
+            let response = response.error_for_status()?;
+            let etag = response
+                .headers()
+                .get(header::ETAG)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from);
+            (fetch_image_bytes(response, url).await?, etag)
+        }
+    };
+    let hash = content_hash(&bytes, &hints.key());
+
+    if let Some(mut entry) = cached.filter(|entry| entry["hash"] == hash) {
+        log_skip("image", &format!("{name} unchanged"));
+        entry["etag"] = json!(etag);
+        return Ok(cache.reuse(name, entry));
+    }
+
+    let image = hints
+        .apply(decode_image(&bytes, source.as_str())?)
+        .with_context(|| format!("bad image hints for {name}"))?;
+    let card_image = write_card_image(&cache.dir, &image, &format!("{name}.{hash}"))?;
+    let changed = cache.entries.contains_key(name);
+    cache.entries.insert(
+        name.to_string(),
+        json!({
+            "sourceUrl": source.as_str(),
+            "etag": etag,
+            "hints": hints.key(),
+            "hash": hash,
+            "encoding": encoding(),
+            "width": card_image.image.width,
+            "height": card_image.image.height,
+            "densities": card_image.densities,
+            "color": card_image.image.color,
+            "placeholder": card_image.image.placeholder,
+            "files": card_image.image.files,
+        }),
+    );
+    cache.used.insert(name.to_string());
+    log_success(
+        "image",
+        &format!(
+            "{name}.{hash}.webp {} ({} files)",
+            if changed { "changed" } else { "downloaded" },
+            card_image.image.files.len()
+        ),
+    );
+    Ok(card_image.image)
+}
+
+/// A schedule image from `site/assets`, converted to WebP.
+pub struct ScheduleImage {
+    /// Site-relative URL of the WebP
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+    /// File name in `cards/`
+    pub file: String,
+}
+
+/// Converts a local `schedule-url` image (a path relative to `site`) to
+/// `cards/{name}.schedule.{hash}.webp`, scaled down to [`SCHEDULE_MAX_WIDTH`].
+///
+/// Errors if the file doesn't exist or isn't an image, so a typo in
+/// tournaments.json fails the build instead of publishing a broken link.
+pub fn convert_schedule_image(path: &str, name: &str, paths: &Paths) -> Result<ScheduleImage> {
+    let ImageSource::Local(path) = ImageSource::from_override(path)? else {
+        bail!("{path} is a URL, not a local schedule image");
+    };
+    let bytes = fs::read(paths.static_file(&path))
+        .with_context(|| format!("site/{path} does not exist"))?;
+    let hash = content_hash(&bytes, &format!("schedule-{SCHEDULE_MAX_WIDTH}"));
+    let file = format!("{name}.schedule.{hash}.webp");
+    let cards_dir = paths.cache("cards");
+    let file_path = format!("{cards_dir}/{file}");
+
+    let (width, height) = match image::image_dimensions(&file_path) {
+        Ok(dimensions) => {
+            log_skip("schedule", &format!("{file} already exists"));
+            dimensions
+        }
+        Err(_) => {
+            let mut image = decode_image(&bytes, &path)?;
+            if image.width() > SCHEDULE_MAX_WIDTH {
+                image = image.resize(SCHEDULE_MAX_WIDTH, u32::MAX, FilterType::Lanczos3);
+            }
+            fs::create_dir_all(&cards_dir)?;
+            fs::write(&file_path, encode_webp(&image)?)
+                .with_context(|| format!("failed to write {file_path}"))?;
+            log_success("schedule", &format!("converted site/{path} to {file}"));
+            (image.width(), image.height())
+        }
+    };
+
+    Ok(ScheduleImage {
+        url: format!("/assets/cards/{file}"),
+        width,
+        height,
+        file,
+    })
+}
+
+/// Reads a response body, rejecting anything that isn't an image.
+async fn fetch_image_bytes(response: reqwest::Response, url: &str) -> Result<Vec<u8>> {
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    if !content_type.starts_with("image/") {
+        bail!("{url} returned {content_type:?}, not an image");
+    }
+    Ok(response.bytes().await?.to_vec())
+}
+
+fn decode_image(bytes: &[u8], url: &str) -> Result<DynamicImage> {
+    let image = image::load_from_memory(bytes)
+        .with_context(|| format!("failed to decode image from {url}"))?;
+    if image.width() == 0 || image.height() == 0 {
+        bail!("{url} is an empty image");
+    }
+    Ok(image)
+}
+
+/// Short hex digest of the source image, how it's processed (`key`) and the
+/// encoder settings, used in file names.
+fn content_hash(bytes: &[u8], key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    hasher.update(key.as_bytes());
+    hasher.update(encoding().as_bytes());
+    let mut hash = hex::encode(hasher.finalize());
+    hash.truncate(HASH_LENGTH);
+    hash
+}
+
+/// A freshly encoded image, plus which densities it was encoded at for the manifest.
+struct EncodedCardImage {
+    image: CardImage,
+    densities: Vec<u32>,
+}
+
+/// Encodes every variant of `image`, naming the files after `stem` (`{name}.{hash}`).
+fn write_card_image(dir: &str, image: &DynamicImage, stem: &str) -> Result<EncodedCardImage> {
+    let mut files = Vec::new();
+    let mut densities = Vec::new();
+    let (mut width, mut height) = (0, 0);
+
+    for density in DENSITIES {
+        let variant_height = CARD_IMAGE_HEIGHT * density;
+        if density > CANONICAL_DENSITY && variant_height > image.height() {
+            continue;
+        }
+        let resized = image.resize(u32::MAX, variant_height, FilterType::Lanczos3);
+        if density == 1 {
+            (width, height) = (resized.width(), resized.height());
+        }
+
+        let webp = encode_webp(&resized)?;
+        if density == CANONICAL_DENSITY {
+            write_card_file(dir, &format!("{stem}.webp"), &webp, &mut files)?;
+        }
+        write_card_file(dir, &format!("{stem}@{density}x.webp"), &webp, &mut files)?;
+        write_card_file(
+            dir,
+            &format!("{stem}@{density}x.avif"),
+            &encode_avif(&resized)?,
+            &mut files,
+        )?;
+        densities.push(density);
+    }
+
+    Ok(EncodedCardImage {
+        image: CardImage {
+            color: dominant_color(image),
+            placeholder: placeholder(image)?,
+            ..card_image(stem, width, height, &densities, files)
+        },
+        densities,
+    })
+}
+
+fn card_image(
+    stem: &str,
+    width: u32,
+    height: u32,
+    densities: &[u32],
+    files: Vec<String>,
+) -> CardImage {
+    let srcset = |extension: &str| {
+        densities
+            .iter()
+            .map(|density| format!("/assets/cards/{stem}@{density}x.{extension} {density}x"))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    CardImage {
+        url: format!("/assets/cards/{stem}.webp"),
+        width,
+        height,
+        srcset_webp: srcset("webp"),
+        srcset_avif: srcset("avif"),
+        color: FALLBACK_COLOR.to_string(),
+        placeholder: String::new(),
+        files,
+    }
+}
+
+fn write_card_file(
+    dir: &str,
+    file_name: &str,
+    contents: &[u8],
+    files: &mut Vec<String>,
+) -> Result<()> {
+    let path = format!("{dir}/{file_name}");
+    fs::write(&path, contents).with_context(|| format!("failed to write {path}"))?;
+    files.push(file_name.to_string());
+    Ok(())
+}
+
+fn encode_webp(image: &DynamicImage) -> Result<Vec<u8>> {
+    let rgba = image.to_rgba8();
+    let encoded = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height()).encode(WEBP_QUALITY);
+    if encoded.is_empty() {
+        bail!("WebP encoder produced no output");
+    }
+    Ok(encoded.to_vec())
+}
+
+fn encode_avif(image: &DynamicImage) -> Result<Vec<u8>> {
+    let rgba = image.to_rgba8();
+    let mut encoded = Vec::new();
+    AvifEncoder::new_with_speed_quality(&mut encoded, AVIF_SPEED, AVIF_QUALITY)
+        .write_image(
+            &rgba,
+            rgba.width(),
+            rgba.height(),
+            image::ExtendedColorType::Rgba8,
+        )
+        .context("AVIF encoding failed")?;
+    Ok(encoded)
+}
+
+/// The most common color in `image`, as `#rrggbb`.
+///
+/// Pixels are bucketed by their top 4 bits per channel so near-identical shades
+/// count together, then the winning bucket's pixels are averaged.
+fn dominant_color(image: &DynamicImage) -> String {
+    let small = image.thumbnail(64, 64);
+    let mut buckets: HashMap<(u8, u8, u8), (u32, [u32; 3])> = HashMap::new();
+    for (_, _, pixel) in small.pixels() {
+        let [r, g, b, a] = pixel.0;
+        if a < 128 {
+            continue;
+        }
+        let bucket = buckets.entry((r >> 4, g >> 4, b >> 4)).or_default();
+        bucket.0 += 1;
+        bucket.1[0] += r as u32;
+        bucket.1[1] += g as u32;
+        bucket.1[2] += b as u32;
+    }
+
+    buckets
+        .into_values()
+        .max_by_key(|(count, _)| *count)
+        .map(|(count, [r, g, b])| format!("#{:02x}{:02x}{:02x}", r / count, g / count, b / count))
+        .unwrap_or_else(|| FALLBACK_COLOR.to_string())
+}
+
+/// A blurred, [`PLACEHOLDER_WIDTH`] pixel wide WebP of `image`, inlined as a `data:` URI.
+fn placeholder(image: &DynamicImage) -> Result<String> {
+    let tiny = image
+        .resize(PLACEHOLDER_WIDTH, u32::MAX, FilterType::Triangle)
+        .blur(1.0);
+    let webp = encode_webp(&tiny)?;
+    Ok(format!(
+        "data:image/webp;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(webp)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_are_urls_or_paths_under_assets() {
+        let local = |value| match ImageSource::from_override(value) {
+            Ok(ImageSource::Local(path)) => Some(path),
+            _ => None,
+        };
+        assert!(matches!(
+            ImageSource::from_override("https://images.start.gg/banner.png"),
+            Ok(ImageSource::Remote(url)) if url == "https://images.start.gg/banner.png"
+        ));
+        for value in [
+            "assets/banners/riptide.png",
+            "/assets/banners/riptide.png",
+            "site/assets/banners/riptide.png",
+        ] {
+            assert_eq!(local(value).as_deref(), Some("assets/banners/riptide.png"));
+        }
+        for value in [
+            "assets/../../ssg/.env",
+            "site/assets/banners/../../index.html",
+            "ssg/src/tournaments.json",
+            "",
+        ] {
+            assert!(ImageSource::from_override(value).is_err(), "{value:?}");
+        }
+    }
+
+    fn hints(overrides: Value, aspect_ratio: f64) -> ImageHints {
+        ImageHints::from_overrides(&overrides, "image", aspect_ratio).unwrap()
+    }
+
+    #[test]
+    fn crop_hint_cuts_out_the_given_rectangle() {
+        let mut image = image::RgbaImage::new(400, 200);
+        image.put_pixel(50, 40, image::Rgba([255, 0, 0, 255]));
+        let hints = hints(
+            json!({ "image-crop": { "x": 50, "y": 40, "width": 100, "height": 60 } }),
+            CARD_ASPECT_RATIO,
+        );
+
+        let cropped = hints.apply(DynamicImage::ImageRgba8(image)).unwrap();
+
+        assert_eq!(cropped.dimensions(), (100, 60));
+        assert_eq!(cropped.get_pixel(0, 0), image::Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn crop_hint_outside_the_image_is_an_error() {
+        let hints = hints(
+            json!({ "image-crop": { "x": 350, "y": 0, "width": 100, "height": 100 } }),
+            CARD_ASPECT_RATIO,
+        );
+        assert!(hints.apply(DynamicImage::new_rgba8(400, 200)).is_err());
+        assert!(ImageHints::from_overrides(
+            &json!({ "image-crop": { "x": 0, "y": 0, "width": 0, "height": 10 } }),
+            "image",
+            CARD_ASPECT_RATIO,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn focal_point_hint_crops_to_the_aspect_ratio_around_the_point() {
+        let mut image = image::RgbaImage::new(400, 100);
+        image.put_pixel(399, 0, image::Rgba([255, 0, 0, 255]));
+        let image = DynamicImage::ImageRgba8(image);
+
+        // Near the right edge: clamped so the crop stays inside the image
+        let right = hints(json!({ "image-focal-point": { "x": 0.9, "y": 0.5 } }), 1.0)
+            .apply(image.clone())
+            .unwrap();
+        assert_eq!(right.dimensions(), (100, 100));
+        assert_eq!(right.get_pixel(99, 0), image::Rgba([255, 0, 0, 255]));
+
+        // Centered on the point otherwise
+        let middle = hints(json!({ "image-focal-point": { "x": 0.5, "y": 0.5 } }), 2.0)
+            .apply(image)
+            .unwrap();
+        assert_eq!(middle.dimensions(), (200, 100));
+
+        assert!(ImageHints::from_overrides(
+            &json!({ "image-focal-point": { "x": 1.5, "y": 0.5 } }),
+            "image",
+            1.0,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn encoding_matches_published_cache_entries() {
+        // Changing this string re-encodes every cached image, so it should only
+        // change along with the encoder settings
+        assert_eq!(
+            encoding(),
+            "webp-q75;avif-q70-s6;densities-1,2,3;height-170;lqip-20"
+        );
+    }
+}
@@ -0,0 +1,37 @@
+//! Builds meleemajors.gg: scrapes start.gg for the tournaments in
+//! tournaments.json, then renders the site, calendar and API, and schedules
+//! emails and notifications. The `ssg` binary is a command line on top of this.
+//!
+//! - [`startgg`] queries start.gg, and [`scrape`] turns tournaments.json into
+//!   scraped tournament data, one `serde_json::Value` per tournament
+//! - [`site`], [`calendar`] and [`api`] render that data as HTML, ICS and the
+//!   public JSON API, whose types are in [`model`]
+//! - [`mailing_list`] schedules emails about it
+//! - [`paths`] says where all of the above read and write files
+
+pub mod api;
+mod broadcast_providers;
+pub mod build;
+pub mod calendar;
+mod changes;
+pub mod check;
+pub mod cli;
+pub mod config;
+mod discord;
+mod email_content;
+pub mod generate_gql;
+pub mod generate_types;
+pub mod images;
+pub mod mailing_list;
+pub mod model;
+pub mod paths;
+mod players;
+mod schedules;
+pub mod scrape;
+pub mod serve;
+pub mod site;
+mod social;
+pub mod startgg;
+pub mod update_rankings;
+pub mod utils;
+mod webhooks;
@@ -1,248 +1,1063 @@
-use crate::utils::{
-    log_heading, log_red, log_success, log_warn, log_yellow, read_file,
-    replace_placeholder_values,
+use std::collections::HashMap;
+
+use crate::broadcast_providers::{self, Broadcast, BroadcastDraft, BroadcastProvider};
+use crate::config::{
+    Config, DigestConfig, NotificationKindPolicy, NotificationPolicy, Offset, SegmentsConfig,
 };
-use anyhow::{anyhow, bail, Context, Result};
-use chrono::{DateTime, NaiveDateTime};
+use crate::email_content::{check_email, render_html, render_text};
+use crate::paths::Paths;
+use crate::utils::{absolute_url, log_error, log_red, log_skip, log_success, log_warn};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Days, NaiveDateTime, Utc};
 use chrono_tz::Tz;
-use reqwest::{
-    header::{self, HeaderMap, HeaderValue},
-    Client,
-};
-use serde_json::{json, to_string_pretty, Value};
+use itertools::Itertools;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 
-pub enum ScheduleBroadcastOutcome {
-    Created,
-    Skipped(&'static str),
+/// Marks broadcasts created by the ssg, so reconciliation never touches ones
+/// written by hand in Kit. Stored in the broadcast's internal description as
+/// `meleemajors:<slug>:<kind>[-<offset>] <content hash>`, or
+/// `meleemajors:digest:<date> <content hash>` for the weekly digest.
+const BROADCAST_KEY_PREFIX: &str = "meleemajors:";
+
+/// Every subject the ssg writes starts with one of these, so providers can
+/// skip broadcasts written by hand without looking them up.
+const SUBJECT_PREFIXES: [&str; 4] = [
+    "Tournament reminder: ",
+    "Top 8 starting ",
+    "Registration closing soon: ",
+    "Upcoming majors: ",
+];
+
+/// Subjects of the broadcasts scheduled before reconciliation existed, which
+/// have no key. They're matched to planned emails by subject instead, and
+/// deleted when nothing matches, so they aren't sent alongside a keyed copy.
+const LEGACY_SUBJECT_PREFIXES: [&str; 2] = ["Tournament reminder: ", "Top 8 starting now: "];
+
+/// Each kind is sent at every offset in its `NotificationPolicy` entry.
+#[derive(Clone, Copy)]
+pub enum BroadcastKind {
+    /// Before the tournament starts
+    Reminder,
+    /// Before (or when) top 8 starts, if `top8-start-time` is set
+    Top8,
+    /// Before registration closes on start.gg
+    RegistrationClosing,
 }
 
-/// Holds all the state and methods needed to interact with a third-party email
-/// provider API for scheduling tournament reminder emails.
-pub struct MailingListService {
-    /// a `reqwest` HTTP client instance configured for the email provider API
-    client: Client,
+impl BroadcastKind {
+    pub const ALL: [BroadcastKind; 3] = [
+        BroadcastKind::Reminder,
+        BroadcastKind::Top8,
+        BroadcastKind::RegistrationClosing,
+    ];
+
+    fn key(&self) -> &'static str {
+        match self {
+            BroadcastKind::Reminder => "reminder",
+            BroadcastKind::Top8 => "top8",
+            BroadcastKind::RegistrationClosing => "registration",
+        }
+    }
 
-    /// must be provided in each individual request for Kit API V3
-    api_secret: String,
+    pub fn label(&self) -> &'static str {
+        match self {
+            BroadcastKind::Reminder => "reminder",
+            BroadcastKind::Top8 => "top 8",
+            BroadcastKind::RegistrationClosing => "registration closing",
+        }
+    }
+
+    pub fn policy<'a>(&self, policy: &'a NotificationPolicy) -> &'a NotificationKindPolicy {
+        match self {
+            BroadcastKind::Reminder => &policy.reminder,
+            BroadcastKind::Top8 => &policy.top8,
+            BroadcastKind::RegistrationClosing => &policy.registration_closing,
+        }
+    }
 }
 
-impl MailingListService {
-    /// Read API token from env and initialize the HTTP client
-    pub fn new() -> Result<Self> {
-        let api_secret = get_kit_api_secret().context("missing API secret")?;
-        let mut headers = HeaderMap::new();
-        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+/// A broadcast the ssg wants scheduled, along with what's needed to match it
+/// up against the provider's existing broadcasts.
+pub struct PlannedEmail {
+    /// e.g. "reminder for Genesis X", for logging
+    pub label: String,
+    /// `meleemajors:<slug>:<kind>`, which stays the same when the content changes
+    pub key: String,
+    /// Where the send time is relevant, e.g. the tournament's timezone
+    pub timezone: Tz,
+    pub draft: BroadcastDraft,
+}
 
-        // Note: Kit V4 API will use standard Auth/bearer token,
-        // but V3 uses a custom query/body param for api secret (??)
+impl PlannedEmail {
+    /// Fills in `draft.description` with `key` and a hash of everything that
+    /// gets sent, so changed emails can be told apart from unchanged ones.
+    fn new(label: String, key: String, timezone: Tz, mut draft: BroadcastDraft) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(draft.send_at.to_rfc3339());
+        hasher.update(&draft.subject);
+        hasher.update(&draft.content);
+        // Not `text_content`, since Kit doesn't take one (see `BroadcastDraft`),
+        // so changing it alone shouldn't update anything
+        hasher.update(draft.tag_ids.iter().join(","));
+        let hash = &hex::encode(hasher.finalize())[..12];
+        draft.description = format!("{key} {hash}");
+        Self {
+            label,
+            key,
+            timezone,
+            draft,
+        }
+    }
+}
+
+/// What `reconcile_broadcasts` did, for logging and tests.
+#[derive(Debug, Default, PartialEq)]
+pub struct Reconciled {
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub deleted: usize,
+}
 
-        // headers.insert(
-        //     header::AUTHORIZATION,
-        //     HeaderValue::from_str(&format!("Bearer {}", api_secret))?,
-        // );
+pub enum PlannedBroadcast {
+    Scheduled(PlannedEmail),
+    Skipped(&'static str),
+}
+
+/// When one of a tournament's notifications goes out and what it leads with.
+/// Both the emails and the Discord posts are sent at these times.
+pub struct Notification {
+    /// e.g. "reminder (5d before) for Genesis X", for logging
+    pub label: String,
+    /// `<kind>`, or `<kind>-<offset>` when the offset isn't zero
+    pub key: String,
+    pub timezone: Tz,
+    pub send_at: DateTime<Utc>,
+    pub subject: String,
+    /// e.g. "This weekend:", shown above the tournament
+    pub intro_text: String,
+}
+
+pub enum PlannedNotification {
+    At(Notification),
+    Skipped(&'static str),
+}
 
-        let client = Client::builder().default_headers(headers).build()?;
+/// Holds all the state and methods needed to schedule tournament reminder
+/// emails with a third-party email provider.
+pub struct MailingListService {
+    /// Kit, or a local directory for testing. See `broadcast_providers::provider_from_env`
+    provider: Box<dyn BroadcastProvider>,
+}
 
-        Ok(Self {
-            client,
-            api_secret,
-        })
+impl MailingListService {
+    /// Pick an email provider based on env vars
+    pub fn new(paths: &Paths) -> Result<Self> {
+        let provider = broadcast_providers::provider_from_env(paths)?;
+        log_success("email", &format!("using {}", provider.name()));
+        Ok(Self { provider })
     }
 
-    /// Schedule a reminder email for 5 days before the tournament starts
-    pub async fn schedule_reminder_broadcast(
+    /// Brings the provider's scheduled broadcasts in line with `tournaments`.
+    ///
+    /// Broadcasts are matched up by key (see `BROADCAST_KEY_PREFIX`), or by
+    /// subject for legacy ones (see `LEGACY_SUBJECT_PREFIXES`). Ones whose
+    /// generated content hasn't changed are left alone (keeping any manual
+    /// edits made in Kit), changed ones are updated in place, missing ones are
+    /// created, and only then are orphans (e.g. for removed tournaments) deleted.
+    pub async fn reconcile_broadcasts(
         &self,
-        tournament_data: &Value,
-    ) -> Result<ScheduleBroadcastOutcome> {
-        // Determine send time (5 days before tournament start)
-        let unix_start_time = tournament_data["start-unix-timestamp"]
-            .as_i64()
-            .context("missing start time")?;
-        let timezone: Tz = tournament_data["timezone"]
-            .as_str()
-            .context("missing timezone")?
-            .parse()?;
-        let start_time = DateTime::from_timestamp(unix_start_time, 0)
-            .context(format!("invalid start time: {}", unix_start_time))?
-            .with_timezone(&timezone);
-        let send_time = start_time - chrono::Duration::days(5);
-
-        // Email subject
-        let tournament_name = tournament_data["name"]
-            .as_str()
-            .context("missing tournament name")?;
-        let subject = format!("Tournament reminder: {}", tournament_name);
+        tournaments: &[Value],
+        config: &Config,
+        paths: &Paths,
+    ) -> Result<Reconciled> {
+        let mut existing: HashMap<String, Vec<Broadcast>> = HashMap::new();
+        let mut legacy: HashMap<String, Vec<Broadcast>> = HashMap::new();
+        for broadcast in self
+            .provider
+            .list_scheduled_broadcasts(&SUBJECT_PREFIXES)
+            .await?
+        {
+            if let Some(key) = broadcast_key(broadcast.description.as_deref()) {
+                existing.entry(key.to_string()).or_default().push(broadcast);
+            } else if LEGACY_SUBJECT_PREFIXES
+                .iter()
+                .any(|prefix| broadcast.subject.starts_with(prefix))
+            {
+                legacy
+                    .entry(broadcast.subject.clone())
+                    .or_default()
+                    .push(broadcast);
+            }
+        }
 
-        // Generate content
-        let content_template =
-            read_file("html/emailMessage.html").replace("{{email-intro-text}}", "This weekend:");
-        let content = replace_placeholder_values(tournament_data, &content_template);
+        let mut reconciled = Reconciled::default();
+        let (emails, failures) = plan_emails(tournaments, config, paths);
+        for email in emails {
+            let label = &email.label;
+            let draft = &email.draft;
+            let result = match existing
+                .get_mut(&email.key)
+                .and_then(|matches| matches.pop())
+                .or_else(|| {
+                    legacy
+                        .get_mut(&draft.subject)
+                        .and_then(|matches| matches.pop())
+                }) {
+                Some(broadcast) if broadcast.description.as_ref() == Some(&draft.description) => {
+                    reconciled.unchanged += 1;
+                    log_skip("email", &format!("{label} unchanged"));
+                    Ok(())
+                }
+                Some(broadcast) => self
+                    .provider
+                    .update_broadcast(&broadcast.id, draft)
+                    .await
+                    .map(|_| {
+                        reconciled.updated += 1;
+                        log_success("email", &format!("{label} updated"));
+                    }),
+                None => self.provider.create_broadcast(draft).await.map(|_| {
+                    reconciled.created += 1;
+                    log_success("email", &format!("{label} created"));
+                }),
+            };
+            if let Err(e) = result {
+                log_error("email", &format!("{label} failed"));
+                log_red(&e.to_string());
+            }
+        }
 
-        self.create_broadcast(&send_time, &subject, &content)
-            .await?;
-        Ok(ScheduleBroadcastOutcome::Created)
+        // Anything left over is for a tournament or kind that no longer gets an
+        // email, or a duplicate of one that was matched above. If anything
+        // failed though, its broadcast would look left over too, so keep it
+        // rather than lose an email that was fine last time
+        if failures > 0 {
+            log_warn(
+                "email",
+                &format!("not deleting old broadcasts, since {failures} emails failed"),
+            );
+            existing.clear();
+            legacy.clear();
+        }
+        for broadcast in existing.into_values().chain(legacy.into_values()).flatten() {
+            match self.provider.delete_broadcast(&broadcast.id).await {
+                Ok(()) => {
+                    reconciled.deleted += 1;
+                    log_success("email", &format!("deleted: {}", broadcast.subject));
+                }
+                Err(e) => {
+                    log_warn(
+                        "email",
+                        &format!("failed to delete broadcast {}: {}", broadcast.id, e),
+                    );
+                }
+            }
+        }
+
+        let Reconciled {
+            created,
+            updated,
+            unchanged,
+            deleted,
+        } = reconciled;
+        log_success(
+            "email",
+            &format!(
+                "{created} created, {updated} updated, {unchanged} unchanged, {deleted} deleted"
+            ),
+        );
+        Ok(reconciled)
     }
+}
 
-    /// Schedule a reminder email for the start of Top 8
-    pub async fn schedule_top8_broadcast(
-        &self,
-        tournament_data: &Value,
-    ) -> Result<ScheduleBroadcastOutcome> {
-        // Parse top 8 start time
-        let top8_start_time_str = tournament_data["top8-start-time"].as_str().unwrap_or("");
-        if top8_start_time_str.is_empty() {
-            return Ok(ScheduleBroadcastOutcome::Skipped(
-                "missing top 8 start time",
-            ));
-        }
-        let top8_datetime_format = "%Y-%m-%d %I:%M%P"; // e.g. "2024-10-06 3:00PM"
-        let timezone: Tz = tournament_data["timezone"]
-            .as_str()
-            .context("missing timezone")?
-            .parse()?;
-        let top8_start_time =
-            NaiveDateTime::parse_from_str(top8_start_time_str, top8_datetime_format)?
-                .and_local_timezone(timezone)
-                .single()
-                .context("invalid top8-start-time")?;
-
-        // Email subject
+/// Every broadcast that should currently be scheduled: each tournament's
+/// reminders, plus the next weekly digest. Skipped and failed ones are logged,
+/// and the number that failed is returned too.
+pub fn plan_emails(
+    tournaments: &[Value],
+    config: &Config,
+    paths: &Paths,
+) -> (Vec<PlannedEmail>, usize) {
+    let mut emails = Vec::new();
+    let mut failures = 0;
+    let mut add = |label: &str, planned: Result<PlannedBroadcast>| match planned {
+        Ok(PlannedBroadcast::Scheduled(email)) => emails.push(email),
+        Ok(PlannedBroadcast::Skipped(reason)) => {
+            log_warn("email", &format!("{label} skipped: {reason}"));
+        }
+        Err(e) => {
+            failures += 1;
+            log_error("email", &format!("{label} failed"));
+            log_red(&format!("{e:#}"));
+        }
+    };
+
+    for tournament_data in tournaments {
         let tournament_name = tournament_data["name"]
             .as_str()
-            .context("missing tournament name")?;
-        let subject = format!("Top 8 starting now: {}", tournament_name);
-
-        // Generate content
-        let content_template = read_file("html/emailMessage.html")
-            .replace("{{email-intro-text}}", "Top 8 starting now:");
-        let content = replace_placeholder_values(tournament_data, &content_template);
-
-        self.create_broadcast(&top8_start_time, &subject, &content)
-            .await?;
-        Ok(ScheduleBroadcastOutcome::Created)
-    }
-
-    /// Delete all scheduled (unsent) broadcasts, paginating through all results.
-    /// Broadcasts that were already sent return 422 and are skipped.
-    /// https://developers.kit.com/v3#list-broadcasts
-    /// https://developers.kit.com/v3#destroy-a-broadcast
-    pub async fn delete_scheduled_broadcasts(&self) -> Result<()> {
-        log_heading("Cleaning up scheduled broadcasts");
-
-        // Page through broadcasts newest-first, deleting each one.
-        // Stop as soon as we hit one that's already sent (422),
-        // since everything older is also sent.
-        let mut deleted = 0;
-        let page = 1;
-        'outer: loop {
-            let response = self
-                .client
-                .get("https://api.convertkit.com/v3/broadcasts")
-                .query(&[
-                    ("api_secret", &self.api_secret),
-                    ("page", &page.to_string()),
-                    ("sort_order", &"desc".to_string()),
-                ])
-                .send()
-                .await?
-                .error_for_status()?;
-            let broadcasts = response.json::<Value>().await?["broadcasts"]
-                .as_array()
-                .context("missing broadcasts field")?
-                .to_vec();
-            if broadcasts.is_empty() {
-                break;
+            .unwrap_or("unknown tournament");
+        let policy = match config.notifications.for_tournament(tournament_data) {
+            Ok(policy) => policy,
+            Err(e) => {
+                add(&format!("emails for {tournament_name}"), Err(e));
+                continue;
             }
-            for broadcast in &broadcasts {
-                let id = broadcast["id"].as_i64().context("missing broadcast id")?;
-                let subject = broadcast["subject"].as_str().unwrap_or("(no subject)");
-                match self.delete_broadcast(id).await {
-                    Ok(true) => {
-                        deleted += 1;
-                        log_success("email", &format!("deleted: {}", subject));
-                    }
-                    Ok(false) => {
-                        // Hit a sent broadcast — everything older is also sent
-                        break 'outer;
-                    }
-                    Err(e) => {
-                        log_warn("email", &format!("failed to delete broadcast {}: {}", id, e));
-                    }
-                }
+        };
+        for kind in BroadcastKind::ALL {
+            let kind_policy = kind.policy(&policy);
+            if !kind_policy.enabled {
+                continue;
+            }
+            for offset in &kind_policy.offsets {
+                add(
+                    &format!("{} for {tournament_name}", label(kind, *offset)),
+                    plan_broadcast(tournament_data, kind, *offset, config, paths),
+                );
             }
-            // Don't increment page — deletions shift results forward,
-            // so page 1 always has the next batch
         }
-        log_success("email", &format!("deleted {} scheduled broadcasts", deleted));
-        Ok(())
     }
+    add(
+        "digest",
+        plan_digest(tournaments, &config.digest, Utc::now(), paths),
+    );
+    (emails, failures)
+}
+
+/// Writes what `reconcile_broadcasts` would schedule to `email-previews/`,
+/// without talking to the email provider. Each email is rendered into
+/// `emailTemplate.html` the way Kit would send it, and `index.html` lists them
+/// all by send time.
+pub fn write_previews(tournaments: &[Value], config: &Config, paths: &Paths) -> Result<()> {
+    let dir = paths.preview("email-previews");
+    if std::fs::metadata(&dir).is_ok() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::create_dir_all(&dir)?;
+
+    let (mut previews, _) = plan_emails(tournaments, config, paths);
+    previews.sort_by_key(|email| email.draft.send_at);
+
+    let mut index_rows = String::new();
+    for email in &previews {
+        let draft = &email.draft;
+        let file_stem = format!(
+            "{}-{}",
+            draft.send_at.format("%Y%m%dT%H%MZ"),
+            email
+                .key
+                .trim_start_matches(BROADCAST_KEY_PREFIX)
+                .replace(':', "-")
+        );
+        // Show the local send time too, since that's what reminder offsets and
+        // the digest schedule are relative to
+        let send_at = format!(
+            "{} ({})",
+            draft.send_at.format("%a %b %-d %Y, %H:%M UTC"),
+            draft
+                .send_at
+                .with_timezone(&email.timezone)
+                .format("%a %b %-d %Y, %-I:%M%P %Z")
+        );
+        let details = format!(
+            "<p><b>Subject:</b> {}</p>\n<p><b>Send at:</b> {send_at}</p>\n<p><a href=\"{file_stem}.txt\">plain text version</a></p>\n<hr>\n",
+            draft.subject
+        );
+        std::fs::write(
+            format!("{dir}/{file_stem}.html"),
+            preview_page(
+                &draft.subject,
+                &(details + &render_email(&draft.content, paths)),
+            ),
+        )?;
+        std::fs::write(format!("{dir}/{file_stem}.txt"), &draft.text_content)?;
+        index_rows.push_str(&format!(
+            "<tr><td>{send_at}</td><td><a href=\"{file_stem}.html\">{}</a></td></tr>\n",
+            draft.subject
+        ));
+        log_success("email", &format!("preview: {file_stem}.html"));
+    }
+
+    let index = format!(
+        "<table cellpadding=\"6\">\n<tr><th>Send at</th><th>Subject</th></tr>\n{index_rows}</table>\n"
+    );
+    std::fs::write(
+        format!("{dir}/index.html"),
+        preview_page("Email previews", &index),
+    )?;
+    log_success(
+        "email",
+        &format!("{} previews written to {dir}/index.html", previews.len()),
+    );
+    Ok(())
+}
+
+/// A broadcast's content inside `emailTemplate.html`, i.e. the email Kit sends.
+/// Kit's own variables are filled with stand-ins.
+pub fn render_email(content: &str, paths: &Paths) -> String {
+    let template = paths.read_template("html/emailTemplate.html");
+    // Drop the notes about copying the template into Kit
+    let template = regex::Regex::new(r"(?s)<!--.*?-->\s*")
+        .unwrap()
+        .replace_all(&template, "");
+    template
+        .replace("{{ message_content }}", content)
+        .replace("{{ unsubscribe_url }}", "#unsubscribe")
+}
+
+fn preview_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n</head>\n<body>\n{body}</body>\n</html>\n"
+    )
+}
+
+/// The broadcast of `kind` that should be scheduled for a tournament `offset`
+/// before the event, if any.
+pub fn plan_broadcast(
+    tournament_data: &Value,
+    kind: BroadcastKind,
+    offset: Offset,
+    config: &Config,
+    paths: &Paths,
+) -> Result<PlannedBroadcast> {
+    let notification = match plan_notification(tournament_data, kind, offset, config)? {
+        PlannedNotification::At(notification) => notification,
+        PlannedNotification::Skipped(reason) => return Ok(PlannedBroadcast::Skipped(reason)),
+    };
+    if notification.send_at < Utc::now() {
+        return Ok(PlannedBroadcast::Skipped("already past send time"));
+    }
+
+    // Generate content
+    let email_data = email_data(tournament_data);
+    let content = render_html(
+        &paths
+            .read_template("html/emailMessage.html")
+            .replace("{{email-intro-text}}", &notification.intro_text),
+        &email_data,
+    )?;
+    let text_content = render_text(
+        &paths
+            .read_template("html/emailMessage.txt")
+            .replace("{{email-intro-text}}", &notification.intro_text),
+        &email_data,
+    );
+    check_email(&content, &text_content)?;
+
+    let slug = tournament_data["start.gg-tournament-name"]
+        .as_str()
+        .context("missing tournament slug")?;
+    Ok(PlannedBroadcast::Scheduled(PlannedEmail::new(
+        notification.label,
+        format!("{BROADCAST_KEY_PREFIX}{slug}:{}", notification.key),
+        notification.timezone,
+        BroadcastDraft {
+            subject: notification.subject,
+            content,
+            text_content,
+            send_at: notification.send_at,
+            description: String::new(),
+            tag_ids: segment_tags(tournament_data, &config.segments),
+        },
+    )))
+}
+
+/// When the notification of `kind` for a tournament `offset` before the event
+/// goes out (after quiet hours), even if that's already past.
+pub fn plan_notification(
+    tournament_data: &Value,
+    kind: BroadcastKind,
+    offset: Offset,
+    config: &Config,
+) -> Result<PlannedNotification> {
+    let tournament_name = tournament_data["name"]
+        .as_str()
+        .context("missing tournament name")?;
+    let timezone: Tz = tournament_data["timezone"]
+        .as_str()
+        .context("missing timezone")?
+        .parse()?;
 
-    /// Returns Ok(true) if deleted, Ok(false) if already sent (422), Err otherwise.
-    async fn delete_broadcast(&self, broadcast_id: i64) -> Result<bool> {
-        let response = self
-            .client
-            .delete(format!(
-                "https://api.convertkit.com/v3/broadcasts/{}",
-                broadcast_id
-            ))
-            .query(&[("api_secret", &self.api_secret)])
-            .send()
-            .await?;
-        match response.status().as_u16() {
-            200..=299 => Ok(true),
-            422 => Ok(false), // already sent/sending
-            code => bail!("Failed to delete broadcast {}: HTTP {}", broadcast_id, code),
+    let (event_time, subject, intro_text) = match kind {
+        BroadcastKind::Reminder => {
+            let unix_start_time = tournament_data["start-unix-timestamp"]
+                .as_i64()
+                .context("missing start time")?;
+            let start_time = DateTime::from_timestamp(unix_start_time, 0)
+                .context(format!("invalid start time: {}", unix_start_time))?
+                .with_timezone(&timezone);
+            let intro_text = if offset.0 <= chrono::Duration::days(6) {
+                "This weekend:"
+            } else {
+                "Coming up:"
+            };
+            (
+                start_time,
+                format!("Tournament reminder: {}", tournament_name),
+                intro_text.to_string(),
+            )
+        }
+        BroadcastKind::Top8 => {
+            // Parse top 8 start time
+            let top8_start_time_str = tournament_data["top8-start-time"].as_str().unwrap_or("");
+            if top8_start_time_str.is_empty() {
+                return Ok(PlannedNotification::Skipped("missing top 8 start time"));
+            }
+            let top8_datetime_format = "%Y-%m-%d %I:%M%P"; // e.g. "2024-10-06 3:00PM"
+            let top8_start_time =
+                NaiveDateTime::parse_from_str(top8_start_time_str, top8_datetime_format)?
+                    .and_local_timezone(timezone)
+                    .single()
+                    .context("invalid top8-start-time")?;
+            let when = if offset.0.is_zero() { "now" } else { "soon" };
+            (
+                top8_start_time,
+                format!("Top 8 starting {when}: {}", tournament_name),
+                format!("Top 8 starting {when}:"),
+            )
+        }
+        BroadcastKind::RegistrationClosing => {
+            let Some(unix_closes_at) =
+                tournament_data["registration-closes-unix-timestamp"].as_i64()
+            else {
+                return Ok(PlannedNotification::Skipped(
+                    "no registration close time on start.gg",
+                ));
+            };
+            let closes_at = DateTime::from_timestamp(unix_closes_at, 0)
+                .context(format!(
+                    "invalid registration close time: {}",
+                    unix_closes_at
+                ))?
+                .with_timezone(&timezone);
+            (
+                closes_at,
+                format!("Registration closing soon: {}", tournament_name),
+                format!(
+                    "Registration closes {}:",
+                    closes_at.format("%A, %B %-d at %-I:%M%P %Z")
+                ),
+            )
+        }
+    };
+
+    let mut send_time = event_time - offset.0;
+    if let Some(quiet_hours) = &config.notifications.quiet_hours {
+        // Top 8 emails are only useful live, so they're never moved, and nothing
+        // is moved past the event it's about (e.g. registration closing at 7am)
+        let adjusted = quiet_hours.adjust(send_time).with_timezone(&timezone);
+        if !matches!(kind, BroadcastKind::Top8) && adjusted <= event_time {
+            send_time = adjusted;
         }
     }
+    let key = if offset.0.is_zero() {
+        kind.key().to_string()
+    } else {
+        format!("{}-{offset}", kind.key())
+    };
+    Ok(PlannedNotification::At(Notification {
+        label: format!("{} for {tournament_name}", label(kind, offset)),
+        key,
+        timezone,
+        send_at: send_time.with_timezone(&Utc),
+        subject,
+        intro_text,
+    }))
+}
 
-    /// https://developers.kit.com/v3#create-a-broadcast
-    async fn create_broadcast(
-        &self,
-        send_time: &DateTime<Tz>,
-        subject: &str,
-        content: &str,
-    ) -> Result<Value> {
-        // Validate send time
-        let send_time_iso8601 = Some(send_time.to_rfc3339());
-        let now = chrono::Utc::now();
-        if send_time < &now {
-            bail!("Already past send time");
-        }
-
-        // Send API request
-        let url = "https://api.convertkit.com/v3/broadcasts";
-        let res = self.client.post(url).json(&json!({
-            "api_secret": &self.api_secret,
-            "email_layout_template": Value::Null, // use default template
-            "content": &content,
-            "subject": &subject,
-            "send_at": &send_time_iso8601,
-            "public": true, // false == draft
-        })).send().await?;
-        let status = res.status();
-        let json = res.json::<Value>().await?;
-        if status.is_success() {
-            Ok(json)
-        } else {
-            let response_code_str = format!("Response code {}", status.as_str());
-            log_red(&response_code_str);
-            log_red(&to_string_pretty(&json)?);
-            Err(anyhow!(response_code_str).context(json))
-        }
-    }
-}
-
-fn get_kit_api_secret() -> Option<String> {
-    let env_key = "KIT_V3_API_SECRET";
-    if let Ok(api_token) = std::env::var(env_key) {
-        Some(api_token)
+/// Tournament data plus the values only emails use.
+fn email_data(tournament_data: &Value) -> Value {
+    let featured_players = (0..8)
+        .filter_map(|i| tournament_data[format!("player{i}")].as_str())
+        .join(", ");
+    let links_text = [
+        ("Bracket", "start.gg-url"),
+        ("Stream", "stream-url"),
+        ("Schedule", "schedule-url"),
+    ]
+    .into_iter()
+    .filter_map(|(label, key)| {
+        let url = tournament_data[key]
+            .as_str()
+            .filter(|url| !url.is_empty())?;
+        Some(format!("{label}: {}", absolute_url(url)))
+    })
+    .join("\n");
+
+    let mut email_data = tournament_data.clone();
+    // Local schedule images link to a page on the site
+    email_data["schedule-url"] = json!(absolute_url(
+        tournament_data["schedule-url"].as_str().unwrap_or("")
+    ));
+    email_data["email-featured-players"] = json!(if featured_players.is_empty() {
+        "TBD".to_string()
     } else {
-        let api_url = "https://app.kit.com/account_settings/developer_settings";
-        log_red("Missing API secret for Kit");
-        log_yellow("Generate one here:");
-        log_yellow(api_url);
-        log_yellow("Then add it to .env or run.sh");
-        log_yellow(&format!("{}=your-api-secret", env_key));
-        None
+        featured_players
+    });
+    email_data["email-links-text"] = json!(links_text);
+    email_data
+}
+
+/// e.g. "reminder (5d before)", for logs
+fn label(kind: BroadcastKind, offset: Offset) -> String {
+    if offset.0.is_zero() {
+        kind.label().to_string()
+    } else {
+        format!("{} ({offset} before)", kind.label())
+    }
+}
+
+/// The tags whose subscribers want emails about a tournament: everyone who
+/// wants every major, plus its region and featured players. Empty (i.e. send
+/// to everyone) when segments are off.
+fn segment_tags(tournament_data: &Value, segments: &SegmentsConfig) -> Vec<u64> {
+    let Some(everything_tag) = segments.everything_tag.filter(|_| segments.enabled) else {
+        return Vec::new();
+    };
+    let mut tags = vec![everything_tag];
+    if let Some(tag) = tournament_data["location-code"]
+        .as_str()
+        .and_then(|location_code| segments.region(location_code))
+        .and_then(|region| region.tag)
+    {
+        tags.push(tag);
+    }
+    let featured_players = (0..8)
+        .filter_map(|i| tournament_data[format!("player{i}")].as_str())
+        .collect_vec();
+    tags.extend(
+        segments
+            .players
+            .iter()
+            .filter(|player| {
+                featured_players
+                    .iter()
+                    .any(|featured| featured.eq_ignore_ascii_case(&player.name))
+            })
+            .map(|player| player.tag),
+    );
+    tags.into_iter().sorted().dedup().collect()
+}
+
+/// Checkboxes for the signup form in `footer.html`, one per tagged region and
+/// player. Kit adds the checked tags to the new subscriber.
+pub fn signup_preferences_html(segments: &SegmentsConfig) -> String {
+    let Some(everything_tag) = segments.everything_tag.filter(|_| segments.enabled) else {
+        return String::new();
+    };
+    let checkbox = |tag: u64, label: &str, checked: &str| {
+        format!(
+            "<label><input type=\"checkbox\" name=\"tags[]\" value=\"{tag}\" onchange=\"emailPreferenceChanged(event)\"{checked}> {label}</label>\n"
+        )
+    };
+    let mut html = String::from(
+        "<fieldset class=\"email-preferences\">\n<legend>send me emails about</legend>\n",
+    );
+    html.push_str(&checkbox(
+        everything_tag,
+        "every major",
+        " checked data-everything",
+    ));
+    for region in &segments.regions {
+        if let Some(tag) = region.tag {
+            html.push_str(&checkbox(tag, &region.name, ""));
+        }
+    }
+    for player in &segments.players {
+        html.push_str(&checkbox(player.tag, &player.name, ""));
+    }
+    html.push_str("</fieldset>\n");
+    html
+}
+
+/// The next weekly digest after `now`, listing every tournament that hasn't
+/// ended by the send time and starts within `digest.weeks` of it.
+pub fn plan_digest(
+    tournaments: &[Value],
+    digest: &DigestConfig,
+    now: DateTime<Utc>,
+    paths: &Paths,
+) -> Result<PlannedBroadcast> {
+    if !digest.enabled {
+        return Ok(PlannedBroadcast::Skipped("disabled in config.json"));
+    }
+    let send_at = next_digest_time(digest, now).context("no valid digest send time")?;
+    let window_end = send_at + chrono::Duration::weeks(digest.weeks.into());
+
+    let mut upcoming = Vec::new();
+    for tournament_data in tournaments {
+        let start = tournament_data["start-unix-timestamp"]
+            .as_i64()
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+            .context("missing start time")?;
+        let end = tournament_data["end-unix-timestamp"]
+            .as_i64()
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+            .context("missing end time")?;
+        if end > send_at && start < window_end {
+            upcoming.push((start, tournament_data));
+        }
+    }
+    if upcoming.is_empty() {
+        return Ok(PlannedBroadcast::Skipped("no tournaments coming up"));
+    }
+    upcoming.sort_by_key(|(start, _)| *start);
+
+    let template_tournament = paths.read_template("html/emailDigestTournament.html");
+    let template_tournament_text = paths.read_template("html/emailDigestTournament.txt");
+    let mut rows = String::new();
+    let mut text_rows = String::new();
+    for (_, tournament_data) in &upcoming {
+        let row_data = email_data(tournament_data);
+        rows.push_str(&render_html(&template_tournament, &row_data)?);
+        text_rows.push_str(&render_text(&template_tournament_text, &row_data));
+        text_rows.push_str("\n\n");
+    }
+
+    let intro_text = match digest.weeks {
+        1 => "Majors in the next week:".to_string(),
+        weeks => format!("Majors in the next {weeks} weeks:"),
+    };
+    let digest_data = json!({ "digest-intro-text": intro_text });
+    let content = render_html(&paths.read_template("html/emailDigest.html"), &digest_data)?
+        .replace("{{digest-tournaments}}", &rows);
+    let text_content = render_text(
+        &paths
+            .read_template("html/emailDigest.txt")
+            .replace("{{digest-tournaments}}", &text_rows),
+        &digest_data,
+    );
+    check_email(&content, &text_content)?;
+    let subject = format!(
+        "Upcoming majors: {}",
+        upcoming
+            .iter()
+            .filter_map(|(_, tournament_data)| tournament_data["name"].as_str())
+            .join(", ")
+    );
+
+    let send_date = send_at.with_timezone(&digest.timezone).date_naive();
+    Ok(PlannedBroadcast::Scheduled(PlannedEmail::new(
+        format!("digest for {}", send_date.format("%b %-d")),
+        format!("{BROADCAST_KEY_PREFIX}digest:{send_date}"),
+        digest.timezone,
+        BroadcastDraft {
+            subject,
+            content,
+            text_content,
+            send_at,
+            description: String::new(),
+            // The digest lists every tournament, so it goes to everyone
+            tag_ids: Vec::new(),
+        },
+    )))
+}
+
+/// The first `digest.weekday` at `digest.time` after `now`. `None` only if
+/// that time doesn't exist two weeks running (e.g. skipped by DST).
+fn next_digest_time(digest: &DigestConfig, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let local_now = now.with_timezone(&digest.timezone);
+    let days_until = (7 + digest.weekday.num_days_from_monday()
+        - local_now.weekday().num_days_from_monday())
+        % 7;
+    [days_until, days_until + 7].into_iter().find_map(|days| {
+        let date = local_now
+            .date_naive()
+            .checked_add_days(Days::new(days.into()))?;
+        date.and_time(digest.time)
+            .and_local_timezone(digest.timezone)
+            .earliest()
+            .map(|send_at| send_at.with_timezone(&Utc))
+            .filter(|send_at| *send_at > now)
+    })
+}
+
+/// `meleemajors:<slug>:<kind>` from a broadcast description, or `None` for
+/// broadcasts the ssg didn't create.
+fn broadcast_key(description: Option<&str>) -> Option<&str> {
+    description
+        .filter(|description| description.starts_with(BROADCAST_KEY_PREFIX))
+        .and_then(|description| description.split_whitespace().next())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcast_providers::LocalProvider;
+    use chrono::Duration;
+
+    /// Scraped data with everything the email templates need.
+    fn tournament(slug: &str, name: &str, start: DateTime<Utc>) -> Value {
+        let timezone = chrono_tz::America::Los_Angeles;
+        let top8 = (start + Duration::days(1)).with_timezone(&timezone);
+        let mut tournament = json!({
+            "start.gg-tournament-name": slug,
+            "name": name,
+            "date": start.with_timezone(&timezone).format("%B %-d").to_string(),
+            "timezone": timezone.name(),
+            "start-unix-timestamp": start.timestamp(),
+            "end-unix-timestamp": (start + Duration::days(2)).timestamp(),
+            "top8-start-time": top8.format("%Y-%m-%d %I:%M%P").to_string(),
+            "image-url": format!("/assets/cards/{slug}.webp"),
+            "entrants": "245",
+            "city-and-state": "San Diego, CA",
+            "location-code": "US-CA",
+            "maps-link": "https://www.google.com/maps/search/?api=1&query=San%20Diego",
+            "start.gg-url": format!("https://www.start.gg/tournament/{slug}/event/melee-singles"),
+            "stream-url": "",
+            "schedule-url": "",
+        });
+        for i in 0..8 {
+            tournament[format!("player{i}")] = Value::Null;
+        }
+        tournament
+    }
+
+    fn draft(subject: &str, send_at: DateTime<Utc>) -> BroadcastDraft {
+        BroadcastDraft {
+            subject: subject.to_string(),
+            content: "<p>Hand written</p>".to_string(),
+            text_content: "Hand written".to_string(),
+            send_at,
+            description: String::new(),
+            tag_ids: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn reconcile_creates_updates_and_deletes_broadcasts() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::for_test(dir.path());
+        let outbox = paths.preview("outbox");
+        let service = MailingListService {
+            provider: Box::new(LocalProvider::new(&outbox).unwrap()),
+        };
+        let config = Config::default();
+        let start = Utc::now() + Duration::days(30);
+        let genesis = tournament("genesis", "Genesis", start);
+        let sapf = tournament("sapf", "SAPF", start + Duration::days(7));
+
+        // Scheduled before broadcasts had keys, for a tournament that's still
+        // listed and for one that isn't
+        let legacy_reminder = service
+            .provider
+            .create_broadcast(&draft("Tournament reminder: Genesis", start))
+            .await
+            .unwrap();
+        service
+            .provider
+            .create_broadcast(&draft("Top 8 starting now: Gone Major", start))
+            .await
+            .unwrap();
+        // Written by hand in Kit
+        let merch = service
+            .provider
+            .create_broadcast(&draft("Merch drop", start))
+            .await
+            .unwrap();
+
+        let reconciled = service
+            .reconcile_broadcasts(&[genesis.clone(), sapf.clone()], &config, &paths)
+            .await
+            .unwrap();
+        // Genesis's legacy reminder is taken over, and its top 8 plus both of
+        // SAPF's emails are new
+        let expected = Reconciled {
+            created: 3,
+            updated: 1,
+            unchanged: 0,
+            deleted: 1,
+        };
+        assert_eq!(reconciled, expected);
+        let scheduled = service
+            .provider
+            .list_scheduled_broadcasts(&SUBJECT_PREFIXES)
+            .await
+            .unwrap();
+        let keys = scheduled
+            .iter()
+            .filter_map(|broadcast| broadcast_key(broadcast.description.as_deref()))
+            .sorted()
+            .collect_vec();
+        assert_eq!(
+            keys,
+            [
+                "meleemajors:genesis:reminder-5d",
+                "meleemajors:genesis:top8",
+                "meleemajors:sapf:reminder-5d",
+                "meleemajors:sapf:top8",
+            ]
+        );
+        assert!(scheduled
+            .iter()
+            .any(|broadcast| broadcast.id == legacy_reminder.id
+                && broadcast_key(broadcast.description.as_deref())
+                    == Some("meleemajors:genesis:reminder-5d")));
+        assert!(std::fs::metadata(format!("{outbox}/{}.eml", merch.id)).is_ok());
+
+        let reconciled = service
+            .reconcile_broadcasts(&[genesis.clone(), sapf.clone()], &config, &paths)
+            .await
+            .unwrap();
+        let expected = Reconciled {
+            unchanged: 4,
+            ..Reconciled::default()
+        };
+        assert_eq!(reconciled, expected);
+
+        // Renamed, and SAPF taken off the list
+        let mut renamed = genesis.clone();
+        renamed["name"] = json!("Genesis 11");
+        let reconciled = service
+            .reconcile_broadcasts(&[renamed], &config, &paths)
+            .await
+            .unwrap();
+        let expected = Reconciled {
+            updated: 2,
+            deleted: 2,
+            ..Reconciled::default()
+        };
+        assert_eq!(reconciled, expected);
+        let subjects = service
+            .provider
+            .list_scheduled_broadcasts(&SUBJECT_PREFIXES)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|broadcast| broadcast.subject)
+            .sorted()
+            .collect_vec();
+        assert_eq!(
+            subjects,
+            [
+                "Top 8 starting now: Genesis 11",
+                "Tournament reminder: Genesis 11",
+            ]
+        );
+        assert!(std::fs::metadata(format!("{outbox}/{}.eml", merch.id)).is_ok());
+    }
+
+    fn utc(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    /// Mondays at 10am in New York, the default
+    fn digest_time(now: &str) -> DateTime<Utc> {
+        next_digest_time(&DigestConfig::default(), utc(now)).unwrap()
+    }
+
+    #[test]
+    fn digest_is_sent_later_the_same_day() {
+        // Monday 9am EDT
+        assert_eq!(
+            digest_time("2026-10-19T13:00:00Z"),
+            utc("2026-10-19T14:00:00Z")
+        );
+    }
+
+    #[test]
+    fn digest_is_sent_next_week_after_the_send_time() {
+        // Monday 10am EDT exactly, then 11am
+        assert_eq!(
+            digest_time("2026-10-19T14:00:00Z"),
+            utc("2026-10-26T14:00:00Z")
+        );
+        assert_eq!(
+            digest_time("2026-10-19T15:00:00Z"),
+            utc("2026-10-26T14:00:00Z")
+        );
+        // Sunday night in New York, but already Monday in UTC
+        assert_eq!(
+            digest_time("2026-10-19T02:00:00Z"),
+            utc("2026-10-19T14:00:00Z")
+        );
+    }
+
+    #[test]
+    fn digest_keeps_local_time_across_dst() {
+        // Clocks go back on Sunday Nov 1, so 10am moves from 14:00 to 15:00 UTC
+        assert_eq!(
+            digest_time("2026-10-27T12:00:00Z"),
+            utc("2026-11-02T15:00:00Z")
+        );
+        // 2:30am doesn't exist on Sunday Mar 8, so it's skipped to the next week
+        let digest = DigestConfig {
+            weekday: chrono::Weekday::Sun,
+            time: chrono::NaiveTime::from_hms_opt(2, 30, 0).unwrap(),
+            ..DigestConfig::default()
+        };
+        assert_eq!(
+            next_digest_time(&digest, utc("2026-03-07T12:00:00Z")),
+            Some(utc("2026-03-15T06:30:00Z"))
+        );
+    }
+
+    fn segments() -> SegmentsConfig {
+        serde_json::from_value(json!({
+            "enabled": true,
+            "everything-tag": 1,
+            "regions": [
+                { "name": "West Coast", "tag": 10, "locations": ["US-CA", "CA-BC"] },
+                { "name": "Europe", "tag": 20, "locations": ["GB", "SE"] },
+                { "name": "South", "tag": null, "locations": ["US-TX"] },
+            ],
+            "players": [
+                { "name": "Cody Schwab", "tag": 100 },
+                { "name": "Zain", "tag": 101 },
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn tagged(location_code: &str, players: &[&str]) -> Value {
+        let mut tournament = tournament("sapf", "SAPF", Utc::now());
+        tournament["location-code"] = json!(location_code);
+        for (i, player) in players.iter().enumerate() {
+            tournament[format!("player{i}")] = json!(player);
+        }
+        tournament
+    }
+
+    #[test]
+    fn segment_tags_pick_region_and_featured_players() {
+        let segments = segments();
+        assert_eq!(segment_tags(&tagged("US-CA", &[]), &segments), [1, 10]);
+        // Countries match whole-country regions, whatever the state
+        assert_eq!(segment_tags(&tagged("SE-AB", &[]), &segments), [1, 20]);
+        // Regions without a tag yet, and places without a region
+        assert_eq!(segment_tags(&tagged("US-TX", &[]), &segments), [1]);
+        assert_eq!(segment_tags(&tagged("JP", &[]), &segments), [1]);
+        assert_eq!(
+            segment_tags(
+                &tagged("US-CA", &["zain", "Jmook", "Cody Schwab"]),
+                &segments
+            ),
+            [1, 10, 100, 101]
+        );
+    }
+
+    #[test]
+    fn segment_tags_are_empty_when_segments_are_off() {
+        let mut segments = segments();
+        segments.enabled = false;
+        assert!(segment_tags(&tagged("US-CA", &["Zain"]), &segments).is_empty());
+        assert!(segment_tags(&tagged("US-CA", &["Zain"]), &SegmentsConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn kit_v3_rejects_tagged_broadcasts() {
+        let provider = crate::broadcast_providers::KitV3Provider::new("secret").unwrap();
+        let tags = segment_tags(&tagged("US-CA", &[]), &segments());
+        let mut tagged_draft = draft("Tournament reminder: SAPF", Utc::now());
+        tagged_draft.tag_ids = tags;
+        let error = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(provider.create_broadcast(&tagged_draft))
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("can't send broadcasts to tags"));
     }
 }
@@ -1,807 +1,58 @@
 extern crate dotenv;
 
-use case_converter::kebab_to_camel;
-use chrono::{DateTime, Utc};
-use chrono_tz::Tz;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 use dotenv::dotenv;
-use ffmpeg_sidecar::command::FfmpegCommand;
-use ffmpeg_sidecar::event::{FfmpegEvent, LogLevel};
-use fs_extra::{copy_items, dir};
-use gql_client::{Client, ClientConfig};
-use icalendar::{Calendar, Class, Component, Event, EventLike};
-use itertools::Itertools;
-use mailing_list::ScheduleBroadcastOutcome;
-use regex::Regex;
-use serde_json::{json, Value};
-use std::collections::{HashMap, HashSet};
-use std::fmt::Debug;
-use std::time::Duration;
-use std::{env, fs};
-use tokio::time::sleep;
-use urlencoding::encode;
-use utils::{
-    absolute_path, log_error, log_green, log_grey, log_heading, log_info, log_red, log_skip,
-    log_success, log_warn, read_file, replace_placeholder_values,
+use meleemajors::cli::{Cli, Command, EmailCommand, GqlCommand, RankingsCommand, TypesCommand};
+use meleemajors::paths::Paths;
+use meleemajors::utils::log_heading;
+use meleemajors::{
+    build, check, cli, generate_gql, generate_types, mailing_list, scrape, serve, update_rankings,
 };
 
-mod generate_gql;
-mod mailing_list;
-mod update_rankings;
-mod utils;
-
-fn read_startgg_api_token() -> String {
-    let token = env::var("STARTGGAPI").expect("STARTGGAPI environmental variable not found!");
-    let token = token.trim();
-    if token.is_empty() {
-        panic!("STARTGGAPI is set but empty. Check the GitHub Actions secret value.");
-    }
-    if token.contains('=') || token.starts_with("STARTGGAPI") {
-        panic!("STARTGGAPI should contain only the token value, not `STARTGGAPI=...`.");
-    }
-
-    token.to_string()
-}
-
 #[tokio::main]
 async fn main() {
     dotenv().ok(); // Read vars from .env file if present
-    let api_token = read_startgg_api_token();
-
-    let args: Vec<String> = std::env::args().collect();
-    if args.contains(&String::from("--generate")) {
-        generate_gql::main();
-        return;
-    }
-
-    if args.contains(&String::from("--update-rankings")) {
-        update_rankings::main()
-            .await
-            .expect("Failed to update rankings");
-        return;
-    }
-    // Whether to exit early for debug after a single iteration without writing
-    // Usage: `cargo run -- --bail`
-    let bail = args.contains(&String::from("--bail"));
-
-    let mut query_headers = HashMap::new();
-    query_headers.insert("authorization".to_string(), format!("Bearer {}", api_token));
-    let query_config = ClientConfig {
-        endpoint: "https://api.start.gg/gql/alpha".to_string(),
-        timeout: Some(60),
-        headers: Some(query_headers),
-        proxy: None,
-    };
-    let query_client = Client::new_with_config(query_config);
-    let query_tournament_info = read_file("graphql/getTournamentInfo.gql");
-    let query_tournament_entrants = read_file("graphql/getTournamentEntrants.gql");
-    let query_featured_players = read_file("graphql/getFeaturedPlayers.gql");
-    let json_featured_players: Value = serde_json::from_str(&read_file("topPlayers.json")).unwrap();
-    let template_header_html = read_file("html/header.html");
-    let mut index_html: String = "".to_string();
-    let template_card = read_file("html/templateCard.html");
-    let index_footer_html = read_file("html/footer.html");
-    let mut calendar_ics = Calendar::new().name("upcoming melee majors").done();
-    let tournaments = read_file("tournaments.json");
-    let json_tournaments: Value = serde_json::from_str(&tournaments).unwrap();
-    let mut all_images: HashSet<String> = HashSet::new();
-    let mut api_tournaments: Vec<Value> = Vec::new();
-
-    let mailing_list = mailing_list::MailingListService::new()
-        .inspect_err(|e| {
-            log_warn("email", "Mailing list service init failed");
-            log_warn("email", "Mailing list service init failed");
-            log_warn("email", &format!("{:?}", e));
-        })
-        .ok();
-
-    if let Some(ref service) = mailing_list {
-        service
-            .delete_scheduled_broadcasts()
-            .await
-            .inspect_err(|e| {
-                log_error("email", "Failed to delete scheduled broadcasts");
-                log_red(&e.to_string());
-            })
-            .ok();
-    }
-
-    // scrape all tournament data
-    let mut all_tournament_data: Vec<Value> = Vec::new();
-    match json_tournaments {
-        Value::Array(vec) => {
-            for tournament in vec.iter() {
-                let tournament_data = scrape_data(
-                    tournament,
-                    query_client.clone(),
-                    &query_tournament_info,
-                    &query_tournament_entrants,
-                    &query_featured_players,
-                    &json_featured_players,
-                    &mut all_images,
-                )
-                .await
-                    .unwrap_or_else(|e| panic!("{e}"));
-
-                all_tournament_data.push(tournament_data);
-            }
-        }
-        _ => panic!("root must be an array"),
-    }
-
-    log_heading("Preparing output");
-
-    // filter out past tournaments and sort by date (soonest first)
-    let now = Utc::now().timestamp();
-    let mut upcoming_tournament_data: Vec<Value> = Vec::new();
-    for tournament_data in all_tournament_data {
-        match tournament_data["end-unix-timestamp"].as_i64() {
-            Some(end) if end <= now => {
-                let url = tournament_data["start.gg-url"]
-                    .as_str()
-                    .unwrap_or("unknown URL");
-                let ended = unix_timestamp_to_log_date(end);
-                log_warn("data", &format!("omitting {url} ended {ended}"));
-            }
-            _ => upcoming_tournament_data.push(tournament_data),
-        }
-    }
-    let mut all_tournament_data = upcoming_tournament_data;
-    all_tournament_data.sort_by_key(|t| t["start-unix-timestamp"].as_i64().unwrap_or(i64::MAX));
-    log_success(
-        "data",
-        &format!("{} upcoming tournaments", all_tournament_data.len()),
-    );
-
-    log_heading("Generating site");
-    for (i, tournament_data) in all_tournament_data.iter().enumerate() {
-        if i == 0 {
-            index_html =
-                replace_placeholder_values(tournament_data, &template_header_html);
-        }
-
-        index_html.push_str(&replace_placeholder_values(
-            tournament_data,
-            &template_card,
-        ));
-
-        calendar_ics = generate_calendar(tournament_data.clone(), &mut calendar_ics);
-        api_tournaments.push(tournament_data.clone());
-
-        if bail {
-            std::process::exit(0)
-        }
-    }
-    index_html.push_str(&format!("\n{}", index_footer_html));
-    cleanup_images(&image_file_names(&all_tournament_data));
-    make_site(&index_html);
-    log_success("html", "wrote index.html");
-    make_calendar(calendar_ics);
-    log_success(
-        "calendar",
-        &format!("generated {} ICS events", api_tournaments.len()),
-    );
-    make_api(&api_tournaments);
-
-    log_heading("Scheduling email");
-    if let Some(ref service) = mailing_list {
-        for tournament_data in all_tournament_data.iter() {
-            schedule_tournament_email(service, tournament_data).await;
-        }
-    } else {
-        log_warn("email", "skipping email scheduling");
-    }
-
-    open_in_browser();
-}
-
-async fn schedule_tournament_email(
-    service: &mailing_list::MailingListService,
-    tournament_data: &Value,
-) {
-    let tournament_name = tournament_data["name"]
-        .as_str()
-        .unwrap_or("unknown tournament");
-
-    match service.schedule_reminder_broadcast(tournament_data).await {
-        Ok(ScheduleBroadcastOutcome::Created) => {
-            log_success("email", &format!("reminder created for {tournament_name}"));
-        }
-        Ok(ScheduleBroadcastOutcome::Skipped(reason)) => {
-            log_warn(
-                "email",
-                &format!("reminder skipped for {tournament_name}: {reason}"),
-            );
-        }
-        Err(e) => {
-            log_error("email", &format!("reminder failed for {tournament_name}"));
-            log_red(&e.to_string());
-        }
-    }
 
-    match service.schedule_top8_broadcast(tournament_data).await {
-        Ok(ScheduleBroadcastOutcome::Created) => {
-            log_success("email", &format!("top 8 created for {tournament_name}"));
-        }
-        Ok(ScheduleBroadcastOutcome::Skipped(reason)) => {
-            log_warn(
-                "email",
-                &format!("top 8 skipped for {tournament_name}: {reason}"),
-            );
-        }
-        Err(e) => {
-            log_error("email", &format!("top 8 failed for {tournament_name}"));
-            log_red(&e.to_string());
-        }
-    }
-}
-
-async fn scrape_data(
-    tournament: &Value,
-    query_client: Client,
-    query_tournament_info: &str,
-    query_tournament_entrants: &str,
-    query_featured_players: &str,
-    featured_players_json: &Value,
-    all_images: &mut HashSet<String>,
-) -> Result<Value, String> {
-    let melee_singles_url = tournament["bracketUrl"].as_str().unwrap();
-    let event_slug = Regex::new(r"^(https?://)?(www\.)?start\.gg/")
-        .unwrap()
-        .replace(melee_singles_url, "");
-
-    let event_slug_parts: Vec<&str> = event_slug.split('/').collect();
-    let tournament_slug = event_slug_parts.get(1).unwrap_or(&"").to_string();
-    let tournament_info_vars = json!({
-      "slug": tournament_slug,
-      "slug_event": event_slug
+    let cli = Cli::parse();
+    let paths = Paths::from_args(&cli.paths).unwrap_or_else(|e| {
+        Cli::command()
+            .error(ErrorKind::MissingRequiredArgument, format!("{e:#}"))
+            .exit()
     });
-
-    let result_tournament_info = graphql_query(
-        query_client.clone(),
-        query_tournament_info,
-        tournament_info_vars.clone(),
-    )
-    .await?;
-
-    let tournament_info = &result_tournament_info["tournament"];
-    // println!("{tournament_info}");
-
-    let name = tournament_info["name"].as_str().unwrap(); // ---> result
-
-    log_heading(name);
-    log_success("start.gg", "scraped tournaments");
-
-    let tournament_entrants_var = json!({
-      "eventId": result_tournament_info["event"].get("id").unwrap().to_string(),
-    });
-    let result_entrant_count = graphql_query(
-        query_client.clone(),
-        query_tournament_entrants,
-        tournament_entrants_var,
-    )
-    .await?;
-    log_success("start.gg", "scraped entrants");
-
-    let featured_players_vars = json!({
-        "slug_event": event_slug
-    });
-    let result_featured_players =
-        graphql_query(query_client, query_featured_players, featured_players_vars)
-            .await?
-            .to_string();
-    log_success("start.gg", "scraped top 8 players");
-
-    let featured_players_top_eight: Vec<Option<String>> = featured_players_json
-        .as_array()
-        .unwrap()
-        .iter()
-        .filter(|player| {
-            result_featured_players.contains(&(player.as_str().unwrap().to_owned() + "\""))
-        })
-        .take(8)
-        .map(|player| Some(player.as_str().unwrap().to_string()))
-        .pad_using(8, |_| None)
-        .collect();
-
-    let entrant_count = result_entrant_count["event"]["numEntrants"].as_number();
-    let entrant_count_string = match entrant_count {
-        Some(entrant_count) => entrant_count.to_string(),
-        None => "TBD".to_string(),
-    }; // ---> result
-
-    let timezone: Tz = tournament_info["timezone"]
-        .as_str()
-        .unwrap()
-        .parse()
-        .expect("Invalid timezone");
-
-    let start_date = unix_timestamp_to_readable_date(&tournament_info["startAt"], timezone);
-
-    let end_date = unix_timestamp_to_readable_date(&tournament_info["endAt"], timezone);
-
-    let date = format!("{start_date} - {end_date}"); // ---> result
-
-    // let city = tournament_info["city"].as_str().unwrap();
-    let city = tournament_info["city"].as_str().unwrap_or("Unknown");
-    let state = tournament_info["addrState"].as_str().unwrap();
-
-    let city_and_state = format!("{}, {}", city, state); // ---> result
-
-    let address = tournament_info["venueAddress"].as_str().unwrap();
-
-    let banner_images = tournament_info["images"].as_array().unwrap();
-    let strip_query = Regex::new(r"\?.*").unwrap();
-
-    // start.gg's Image.type is a free-form String with no documented enum. Empirically we see
-    // "banner" (wide) and "profile" (square icon). Prefer matching the explicit type, and fall
-    // back to max/min width so a tournament with unexpected type values still gets an image.
-    let banner_image = banner_images
-        .iter()
-        .find(|img| img["type"].as_str() == Some("banner"))
-        .or_else(|| {
-            banner_images
-                .iter()
-                .max_by_key(|img| img["width"].as_u64().unwrap_or(0))
-        })
-        .unwrap();
-    let banner_url = strip_query.replace(banner_image["url"].as_str().unwrap(), "");
-
-    let profile_image = banner_images
-        .iter()
-        .find(|img| img["type"].as_str() == Some("profile"))
-        .or_else(|| {
-            // Only fall back to a second distinct image — if there's just one, it's the banner.
-            if banner_images.len() > 1 {
-                banner_images
-                    .iter()
-                    .min_by_key(|img| img["width"].as_u64().unwrap_or(u64::MAX))
-            } else {
-                None
-            }
-        });
-
-    let name_camel = kebab_to_camel(&tournament_slug);
-
-    download_tournament_image(&banner_url, &name_camel, all_images);
-    let thumbnail_url = profile_image.map(|img| {
-        let url = strip_query.replace(img["url"].as_str().unwrap(), "");
-        let thumb_name = format!("{name_camel}.thumbnail");
-        download_tournament_image(&url, &thumb_name, all_images);
-        format!("/assets/cards/{thumb_name}.webp")
-    });
-
-    let stream_url = resolve_stream_url(tournament, &tournament_info["streams"]);
-    let schedule_url = tournament["schedule-url"].as_str().unwrap_or("");
-
-    let stream_link_class = if stream_url.is_empty() { " hidden" } else { "" };
-    let image_url = format!("/assets/cards/{}.webp", name_camel);
-
-    Ok(json!({
-        "start.gg-tournament-name": name_camel,
-        "image-url": image_url,
-        "image-url-thumbnail": thumbnail_url,
-        "name": check_override(tournament, name.to_string(), "name"),
-        "date": date,
-        "start-unix-timestamp": tournament_info["startAt"],
-        "end-unix-timestamp": tournament_info["endAt"],
-        "timezone": tournament_info["timezone"],
-        "player0": check_override_nullable(tournament, featured_players_top_eight[0].clone(), "player0"),
-        "player1": check_override_nullable(tournament, featured_players_top_eight[1].clone(), "player1"),
-        "player2": check_override_nullable(tournament, featured_players_top_eight[2].clone(), "player2"),
-        "player3": check_override_nullable(tournament, featured_players_top_eight[3].clone(), "player3"),
-        "player4": check_override_nullable(tournament, featured_players_top_eight[4].clone(), "player4"),
-        "player5": check_override_nullable(tournament, featured_players_top_eight[5].clone(), "player5"),
-        "player6": check_override_nullable(tournament, featured_players_top_eight[6].clone(), "player6"),
-        "player7": check_override_nullable(tournament, featured_players_top_eight[7].clone(), "player7"),
-        "entrants": entrant_count_string,
-        "city-and-state": check_override(tournament, city_and_state.to_string(), "city-and-state"),
-        "maps-link": check_override(
-          tournament,
-          format!("https://www.google.com/maps/search/?api=1&query={}", encode(address)),
-          "maps-link"
-        ),
-        "full-address": address,
-        "start.gg-url": melee_singles_url,
-        "stream-url": stream_url,
-        "schedule-url": schedule_url,
-        "schedule-link-class": if schedule_url.is_empty() {" hidden"} else {""},
-        "stream-link-class": stream_link_class,
-        "top8-start-time": tournament["top8-start-time"],
-    }))
-}
-
-// Manual `stream-url` override in tournaments.json wins when set to a non-empty
-// string; otherwise the first stream returned by start.gg is used.
-fn resolve_stream_url(tournament: &Value, streams: &Value) -> String {
-    if let Some(override_url) = tournament.get("stream-url").and_then(|v| v.as_str()) {
-        if !override_url.is_empty() {
-            return override_url.to_string();
-        }
-    }
-
-    streams
-        .as_array()
-        .and_then(|arr| arr.iter().find_map(stream_to_url))
-        .unwrap_or_default()
-}
-
-fn stream_to_url(stream: &Value) -> Option<String> {
-    let name = stream.get("streamName").and_then(|v| v.as_str())?;
-    if name.is_empty() {
-        return None;
-    }
-    match stream.get("streamSource").and_then(|v| v.as_str()) {
-        Some("TWITCH") => Some(format!("https://www.twitch.tv/{name}")),
-        Some("YOUTUBE") => Some(format!("https://www.youtube.com/{name}")),
-        Some(other @ ("HITBOX" | "STREAMME" | "MIXER")) => {
-            log_warn(
-                "start.gg",
-                &format!("deprecated streamSource {other:?} for stream {name:?}; ignoring"),
-            );
-            None
-        }
-        Some(other) => {
-            log_warn(
-                "start.gg",
-                &format!("unrecognized streamSource {other:?} for stream {name:?}; ignoring"),
-            );
-            None
-        }
-        None => {
-            log_warn(
-                "start.gg",
-                &format!("missing streamSource for stream {name:?}; ignoring"),
-            );
-            None
-        }
-    }
-}
-
-fn check_override(tournament: &Value, default_value: String, default_key: &str) -> String {
-    let obj_tournament = tournament.as_object().unwrap();
-    if obj_tournament.contains_key(default_key) {
-        return format!("{}", obj_tournament[default_key].as_str().unwrap());
-    }
-    return format!("{}", default_value);
-}
-
-fn check_override_nullable(
-    tournament: &Value,
-    default_value: Option<String>,
-    default_key: &str,
-) -> Option<String> {
-    tournament
-        .as_object()
-        .and_then(|obj| obj.get(default_key))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-        .or(default_value)
-}
-
-async fn graphql_query(client: Client, query: &str, vars: Value) -> Result<Value, String> {
-    const MAX_ATTEMPTS: usize = 5;
-
-    for attempt in 1..=MAX_ATTEMPTS {
-        match client
-            .query_with_vars_unwrap::<Value, Value>(query, vars.clone())
+    match cli.command {
+        Command::Build(args) => build::main(args, &paths).await,
+        Command::Rankings {
+            command: RankingsCommand::Update { top_players },
+        } => update_rankings::main(&cli::input_path(&top_players, &paths, "topPlayers.json"))
             .await
-        {
-            Ok(data) => {
-                return Ok(data);
-            }
-            Err(e) => {
-                let error = format_graphql_error(&e);
-                if is_unauthorized_error(&error) {
-                    return Err(format!(
-                        "start.gg GraphQL query failed with 401 Unauthorized. Check STARTGGAPI. Error details: {error}"
-                    ));
-                }
-
-                if attempt == MAX_ATTEMPTS {
-                    return Err(format!(
-                        "start.gg GraphQL query failed after {MAX_ATTEMPTS} attempts. Last error: {error}"
-                    ));
-                }
-
-                println!("Error while querying: {error}");
-                println!("Retrying in 10 seconds... ({attempt}/{MAX_ATTEMPTS})");
-                sleep(Duration::from_secs(10)).await;
-            }
-        }
-    }
-
-    unreachable!()
-}
-
-fn format_graphql_error(error: &impl Debug) -> String {
-    format!("{error:?}")
-}
-
-fn is_unauthorized_error(error: &str) -> bool {
-    error.contains("[401]") || error.contains("401") && error.contains("Unauthorized")
-}
-
-fn unix_timestamp_to_readable_date(date: &Value, timezone: Tz) -> String {
-    DateTime::from_timestamp(date.as_i64().unwrap(), 0)
-        .unwrap()
-        .with_timezone(&timezone)
-        .format("%B %d")
-        .to_string()
-}
-
-fn unix_timestamp_to_log_date(timestamp: i64) -> String {
-    DateTime::from_timestamp(timestamp, 0)
-        .map(|date| date.format("%B %-d").to_string())
-        .unwrap_or_else(|| format!("invalid timestamp {timestamp}"))
-}
-
-fn download_tournament_image(url: &str, name: &str, image_data: &mut HashSet<String>) {
-    // ffmpeg -i "image_url" -vf "scale=-1:340" "tournament_name".webp
-
-    fs::create_dir_all(absolute_path("cards")).unwrap();
-    let image_path = absolute_path(&format!("cards/{name}.webp"));
-
-    image_data.insert(format!("{name}.webp"));
-
-    if fs::metadata(&image_path).is_ok() {
-        log_skip("ffmpeg", &format!("{name}.webp already exists"));
-    } else {
-        println!("[ffmpeg] downloading {url}");
-        FfmpegCommand::new()
-            .input(url)
-            .args(["-vf", "scale=-1:340"])
-            .overwrite()
-            .output(image_path)
-            .spawn()
-            .unwrap()
-            .iter()
-            .unwrap()
-            .for_each(|event| match event {
-                FfmpegEvent::Log(LogLevel::Error | LogLevel::Fatal, msg) => {
-                    log_error("ffmpeg", &format!("{:?}", msg));
-                }
-                FfmpegEvent::Progress(progress) => {
-                    log_info("ffmpeg", &format!("{:?}", progress));
-                }
-                FfmpegEvent::Done => {
-                    log_success("ffmpeg", &format!("{name}.webp downloaded"));
-                }
-                _ => {}
-            });
-    }
-}
-
-fn generate_calendar(tournament_data: Value, calendar_ics: &mut Calendar) -> Calendar {
-    return calendar_ics
-        .push(
-            Event::new()
-                .starts(
-                    DateTime::from_timestamp(
-                        tournament_data["start-unix-timestamp"]
-                            .as_number()
-                            .unwrap()
-                            .as_i64()
-                            .unwrap(),
-                        0,
-                    )
-                    .unwrap()
-                    .date_naive(),
-                )
-                .ends(
-                    DateTime::from_timestamp(
-                        tournament_data["end-unix-timestamp"]
-                            .as_number()
-                            .unwrap()
-                            .as_i64()
-                            .unwrap(),
-                        0,
-                    )
-                    .unwrap()
-                    .date_naive(),
-                )
-                .summary(tournament_data["name"].as_str().unwrap())
-                .description(&replace_placeholder_values(
-                    &tournament_data,
-                    "{{start.gg-url}}\n\nattendees: {{entrants}}\n\nnotable entrants:\n\n{{player0}}\n{{player1}}\n{{player2}}\n{{player3}}\n{{player4}}\n{{player5}}\n{{player6}}\n{{player7}}\n",
-                ))
-                .class(Class::Public)
-                .location(tournament_data["full-address"].as_str().unwrap())
-                .uid(&uuid::Uuid::new_v5(
-                    &uuid::Uuid::NAMESPACE_DNS,
-                    format!(
-                        "{}@meleemajors.gg",
-                        tournament_data["start.gg-tournament-name"]
-                            .as_str()
-                            .unwrap()
-                    )
-                    .as_bytes(),
-                )
-                .to_string())
-                .done(),
-        )
-        .done();
-}
-
-fn make_site(index_html: &str) {
-    fs::write(absolute_path("../../site/index.html"), index_html).unwrap();
-    let site_cards_path = absolute_path("../../site/assets/cards");
-    match fs::remove_dir_all(&site_cards_path) {
-        Ok(_) => {}
-        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
-        Err(e) => panic!("failed to remove {site_cards_path}: {e}"),
-    }
-
-    copy_items(
-        &[absolute_path("cards")],
-        absolute_path("../../site/assets/"),
-        &dir::CopyOptions::new().overwrite(true),
-    )
-    .unwrap();
-}
-
-fn make_calendar(calendar_ics: Calendar) {
-    fs::write(
-        absolute_path("../../site/calendar.ics"),
-        calendar_ics.to_string(),
-    )
-    .unwrap();
-}
-
-fn make_api(tournaments: &[Value]) {
-    let mut sorted = tournaments.to_vec();
-    sorted.sort_by_key(|t| t["start-unix-timestamp"].as_i64().unwrap_or(i64::MAX));
-    let api_tournaments: Vec<Value> = sorted.iter().map(tournament_to_api).collect();
-    let payload = json!({
-        "$schema": "tournaments.schema.json",
-        "lastUpdated": Utc::now().to_rfc3339(),
-        "tournaments": api_tournaments,
-    });
-
-    validate_api_payload(&payload);
-
-    let out_path = absolute_path("../../site/api/v1/tournaments.json");
-    fs::create_dir_all(std::path::Path::new(&out_path).parent().unwrap()).unwrap();
-    fs::write(&out_path, serde_json::to_string_pretty(&payload).unwrap()).unwrap();
-    log_success("api", "wrote /api/v1/tournaments.json");
-}
-
-fn validate_api_payload(payload: &Value) {
-    let schema_path = absolute_path("../../site/api/v1/tournaments.schema.json");
-    let schema: Value = serde_json::from_str(&fs::read_to_string(&schema_path).unwrap())
-        .expect("tournaments.schema.json is not valid JSON");
-    let validator = jsonschema::validator_for(&schema)
-        .expect("tournaments.schema.json is not a valid JSON Schema");
-
-    let errors: Vec<String> = validator
-        .iter_errors(payload)
-        .map(|e| format!("  at {}: {}", e.instance_path, e))
-        .collect();
-
-    if errors.is_empty() {
-        log_success("api", "validated against tournaments.schema.json");
-    } else {
-        log_error("api", "payload failed schema validation:");
-        for err in &errors {
-            log_red(err);
-        }
-        panic!("tournaments.json does not match tournaments.schema.json");
-    }
-}
-
-fn tournament_to_api(t: &Value) -> Value {
-    let players: Vec<Value> = (0..8).map(|i| t[format!("player{}", i)].clone()).collect();
-
-    let start_timestamp = t["start-unix-timestamp"]
-        .as_i64()
-        .and_then(|ts| DateTime::from_timestamp(ts, 0))
-        .map(|dt| dt.to_rfc3339())
-        .unwrap_or_default();
-
-    let end_timestamp = t["end-unix-timestamp"]
-        .as_i64()
-        .and_then(|ts| DateTime::from_timestamp(ts, 0))
-        .map(|dt| dt.to_rfc3339())
-        .unwrap_or_default();
-
-    let image_url = format!(
-        "https://meleemajors.gg{}",
-        t["image-url"].as_str().unwrap_or("")
-    );
-
-    let thumbnail_url = t["image-url-thumbnail"]
-        .as_str()
-        .map(|path| Value::String(format!("https://meleemajors.gg{path}")))
-        .unwrap_or(Value::Null);
-
-    let entrants = t["entrants"].as_str().and_then(|s| s.parse::<u64>().ok());
-
-    let non_empty = |key: &str| -> Value {
-        match t[key].as_str() {
-            Some(s) if !s.is_empty() => Value::String(s.to_string()),
-            _ => Value::Null,
-        }
-    };
-
-    let startgg_url = t["start.gg-url"].as_str().unwrap_or("");
-    let startgg_details_url = startgg_url
-        .split_once("/event/")
-        .map(|(base, _)| format!("{base}/details"));
-
-    json!({
-        "name": t["name"],
-        "slug": t["start.gg-tournament-name"],
-        "startTimestamp": start_timestamp,
-        "endTimestamp": end_timestamp,
-        "dateString": t["date"],
-        "timezone": t["timezone"],
-        "top8StartTime": non_empty("top8-start-time"),
-        "entrants": entrants,
-        "players": players,
-        "cityAndState": t["city-and-state"],
-        "fullAddress": t["full-address"],
-        "mapsLink": t["maps-link"],
-        "bracketUrl": t["start.gg-url"],
-        "tournamentUrl": startgg_details_url.clone(),
-        "streamUrl": non_empty("stream-url"),
-        "scheduleUrl": non_empty("schedule-url"),
-        "imageUrl": image_url,
-        "thumbnailUrl": thumbnail_url,
-        // Deprecated: superseded by `slug`. Kept for backwards compatibility.
-        "startggTournamentName": t["start.gg-tournament-name"],
-        // Deprecated: superseded by `bracketUrl`. Kept for backwards compatibility.
-        "startggUrl": t["start.gg-url"],
-        // Deprecated: superseded by `tournamentUrl`. Kept for backwards compatibility.
-        "startggDetailsUrl": startgg_details_url,
-    })
-}
-
-fn image_file_names(tournaments: &[Value]) -> HashSet<String> {
-    let mut image_names = HashSet::new();
-    for tournament in tournaments {
-        for key in ["image-url", "image-url-thumbnail"] {
-            if let Some(image_url) = tournament[key].as_str() {
-                if let Some(file_name) = image_url.split('/').next_back() {
-                    image_names.insert(file_name.to_string());
-                }
+            .expect("Failed to update rankings"),
+        Command::Gql {
+            command: GqlCommand::Generate { top_players },
+        } => generate_gql::main(
+            &cli::input_path(&top_players, &paths, "topPlayers.json"),
+            &paths,
+        ),
+        Command::Types {
+            command: TypesCommand::Generate,
+        } => generate_types::main(&paths),
+        Command::Check(inputs) => {
+            if !check::main(&inputs, &paths) {
+                std::process::exit(1);
             }
         }
-    }
-
-    image_names
-}
-
-fn cleanup_images(data: &HashSet<String>) {
-    let cards_path = absolute_path("cards");
-    fs::create_dir_all(&cards_path).unwrap();
-
-    let images = fs::read_dir(cards_path).unwrap();
-    images.for_each(|image| {
-        let image_path = image.unwrap().path();
-        let image_str = image_path
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
-        if !data.contains(&image_str) {
-            fs::remove_file(image_path).ok();
-        };
-    })
-}
-
-fn open_in_browser() {
-    log_green("\n🎉 Finished 🎉\n");
-    log_grey("Next steps:");
-    log_grey("1. git commit & push to main to deploy site");
-    log_grey("2. Review scheduled emails: https://app.kit.com/campaigns");
-
-    let index_path = absolute_path("../../site/index.html");
-    let file_url = format!("file://{}", index_path);
-
-    log_grey("3. Preview in web browser");
-
-    if let Err(e) = webbrowser::open(&file_url) {
-        log_warn("browser", &format!("Failed to open browser: {}", e));
-        log_grey(&format!("Please manually open: {}", index_path));
+        Command::Email {
+            command: EmailCommand::Preview(args),
+        } => {
+            let config = args
+                .inputs
+                .load_config(&paths)
+                .unwrap_or_else(|e| panic!("{e:#}"));
+            let (tournaments, _) = scrape::scrape_live(&args, &paths).await;
+            log_heading("Previewing email");
+            mailing_list::write_previews(&tournaments, &config, &paths)
+                .unwrap_or_else(|e| panic!("Failed to write email previews: {e:#}"));
+        }
+        Command::Serve(args) => serve::main(args, &paths).await,
     }
 }
@@ -0,0 +1,7 @@
+//! The public API's types, generated from tournaments.schema.json by
+//! `cargo run -- types generate`. [`crate::api::tournament_to_api`] and
+//! [`crate::api::api_payload`] build them from scraped tournament data.
+
+#![allow(deprecated)]
+
+include!("model/tournaments.rs");
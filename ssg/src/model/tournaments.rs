@@ -0,0 +1,235 @@
+// @generated by ssg/src/generate_types.rs from tournaments.schema.json. Do not edit by hand.
+
+use serde::{Deserialize, Serialize};
+
+/// Public listing of upcoming Super Smash Bros. Melee majors, as served from https://meleemajors.gg/api/v1/tournaments.json. Regenerated on every site build.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TournamentsResponse {
+    /// Reference to the JSON Schema this payload conforms to, resolved relative to the payload's own URL. Present so editors and validators can auto-discover the schema without hard-coding a host.
+    #[serde(rename = "$schema")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// RFC 3339 timestamp of the build in which tournament data last changed. Stays the same across builds that scrape identical data, so it is safe to use for caching and change detection.
+    #[serde(rename = "lastUpdated")]
+    pub last_updated: String,
+    /// RFC 3339 timestamp of when this payload was generated. Updates on every site build, even when tournament data is unchanged.
+    #[serde(rename = "generatedAt")]
+    pub generated_at: String,
+    /// All tracked tournaments, ordered chronologically by start date (soonest first).
+    pub tournaments: Vec<Tournament>,
+}
+
+/// Rolling history of what changed between site builds, served from https://meleemajors.gg/api/v1/changes.json. A build only adds an entry when tournament data actually changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeLog {
+    /// Same value as `lastUpdated` in tournaments.json: when tournament data last changed.
+    #[serde(rename = "lastUpdated")]
+    pub last_updated: String,
+    /// Entries older than this many days are dropped from the log.
+    #[serde(rename = "retentionDays")]
+    pub retention_days: u64,
+    /// One entry per build that changed tournament data, newest first.
+    pub entries: Vec<ChangeLogEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeLogEntry {
+    /// RFC 3339 timestamp of the build that detected these changes.
+    pub timestamp: String,
+    /// Tournaments that appeared since the previous build.
+    pub added: Vec<TournamentSummary>,
+    /// Tournaments that disappeared since the previous build, either because they ended or were dropped from the listing.
+    pub removed: Vec<TournamentSummary>,
+    /// Tournaments present in both builds whose data differs.
+    pub changed: Vec<TournamentChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TournamentSummary {
+    /// Same value as the tournament's `slug`.
+    pub slug: String,
+    /// Same value as the tournament's `name`.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TournamentChange {
+    /// Same value as the tournament's `slug`.
+    pub slug: String,
+    /// The tournament's `name` as of this build.
+    pub name: String,
+    /// Each top-level tournament field whose value changed. Deprecated aliases are omitted.
+    pub fields: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// Name of the changed tournament field.
+    pub field: String,
+    /// Value in the previous build.
+    pub from: serde_json::Value,
+    /// Value in this build.
+    pub to: serde_json::Value,
+}
+
+/// Body of every outbound webhook request. `tournament` is as of this build, or as of the previous build for `removed` and `ended` events.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    /// Unique per event and endpoint, and the same across retries, so duplicate deliveries can be ignored.
+    pub id: String,
+    /// What happened to the tournament. `removed` means it was taken off the schedule before it ended.
+    pub event: String,
+    /// When the build that found the event ran.
+    pub timestamp: String,
+    pub tournament: Tournament,
+    /// What changed, for `changed` events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Vec<FieldChange>>,
+}
+
+/// Lightweight listing served from https://meleemajors.gg/api/v1/index.json. Poll this and compare each entry's hash to decide which per-tournament resources to re-fetch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Index {
+    /// Same value as `lastUpdated` in tournaments.json: when tournament data last changed.
+    #[serde(rename = "lastUpdated")]
+    pub last_updated: String,
+    /// One entry per tracked tournament, ordered chronologically by start date (soonest first).
+    pub tournaments: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Same value as the tournament's `slug`.
+    pub slug: String,
+    /// Absolute URL of the per-tournament resource, which conforms to #/$defs/tournament.
+    pub url: String,
+    /// RFC 3339 timestamp of the build in which this tournament's data last changed.
+    #[serde(rename = "lastModified")]
+    pub last_modified: String,
+    /// SHA-256 of the resource's compact JSON serialization. Changes whenever any field of the tournament changes.
+    pub hash: String,
+}
+
+/// Per-player index served from https://meleemajors.gg/api/v1/players.json, listing which upcoming majors each ranked player is registered for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Players {
+    /// Same value as `lastUpdated` in tournaments.json: when tournament data last changed.
+    #[serde(rename = "lastUpdated")]
+    pub last_updated: String,
+    /// Every tracked player, ordered by rank. Players who aren't registered for any upcoming major have an empty `tournaments` list.
+    pub players: Vec<Player>,
+}
+
+/// A ranked player and the upcoming majors they're registered for. Also served individually from https://meleemajors.gg/api/v1/players/<slug>.json.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Player {
+    /// Player tag as it appears in tournaments' `players` lists.
+    pub name: String,
+    /// URL-safe identifier derived from the tag. Stable across builds.
+    pub slug: String,
+    /// Position in meleemajors.gg's tracked player list: the latest SSBMRank top 50, followed by notable players who have dropped out of it.
+    pub rank: u64,
+    /// Absolute URL of this player's individual resource.
+    pub url: String,
+    /// Absolute URL of an iCalendar feed containing only the majors this player is registered for.
+    #[serde(rename = "calendarUrl")]
+    pub calendar_url: String,
+    /// Upcoming majors the player is registered for, ordered chronologically by start date (soonest first).
+    pub tournaments: Vec<PlayerAppearance>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerAppearance {
+    /// Same value as the tournament's `slug`.
+    pub slug: String,
+    /// Same value as the tournament's `name`.
+    pub name: String,
+    /// Same value as the tournament's `startTimestamp`.
+    #[serde(rename = "startTimestamp")]
+    pub start_timestamp: String,
+    /// Same value as the tournament's `endTimestamp`.
+    #[serde(rename = "endTimestamp")]
+    pub end_timestamp: String,
+    /// Same value as the tournament's `dateString`.
+    #[serde(rename = "dateString")]
+    pub date_string: String,
+    /// Same value as the tournament's `cityAndState`.
+    #[serde(rename = "cityAndState")]
+    pub city_and_state: String,
+    /// Same value as the tournament's `bracketUrl`.
+    #[serde(rename = "bracketUrl")]
+    pub bracket_url: String,
+    /// Absolute URL of the tournament's individual resource.
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tournament {
+    /// Human-readable tournament name as displayed on the site (e.g. "Riptide 2026", "Pat's House 5").
+    pub name: String,
+    /// Internal slug-like identifier derived from the tournament's URL. Stable across builds and safe to use as a key.
+    pub slug: String,
+    /// RFC 3339 timestamp for when the tournament begins (typically the first day's check-in or first match). UTC-offset is the event's local offset.
+    #[serde(rename = "startTimestamp")]
+    pub start_timestamp: String,
+    /// RFC 3339 timestamp for when the tournament ends, as reported by start.gg. Reflected verbatim without any end-of-day rounding.
+    #[serde(rename = "endTimestamp")]
+    pub end_timestamp: String,
+    /// Pre-formatted human-readable date range intended for display (e.g. "April 10 - April 12"). Format is not machine-parseable; use startTimestamp for programmatic date handling.
+    #[serde(rename = "dateString")]
+    pub date_string: String,
+    /// IANA timezone name for the tournament venue. Pair with startTimestamp to render local times.
+    pub timezone: String,
+    /// Announced start time of Top 8, in the organizer's preferred format. Free-form; null if not yet scheduled.
+    #[serde(rename = "top8StartTime")]
+    pub top8_start_time: Option<String>,
+    /// Total registered entrants in the main singles bracket. null if registration has not opened or the count is otherwise unknown.
+    pub entrants: Option<u64>,
+    /// Fixed-length list of the top 8 seeded/notable entrants, ordered by seed. Slots that have not yet been announced are null.
+    pub players: Vec<Option<String>>,
+    /// Short locality string for display (city + state/region). Free-form; format varies by country.
+    #[serde(rename = "cityAndState")]
+    pub city_and_state: String,
+    /// Full postal address of the venue, as provided by the organizer. Free-form.
+    #[serde(rename = "fullAddress")]
+    pub full_address: String,
+    /// Google Maps search URL for the venue address. Derived from fullAddress.
+    #[serde(rename = "mapsLink")]
+    pub maps_link: String,
+    /// Canonical start.gg URL for the main Melee singles event page.
+    #[serde(rename = "bracketUrl")]
+    pub bracket_url: String,
+    /// start.gg URL for the tournament's overview /details page, which lists all events and general info. Prefer this over bracketUrl for general-purpose linking. null if the details URL cannot be derived from bracketUrl.
+    #[serde(rename = "tournamentUrl")]
+    pub tournament_url: Option<String>,
+    /// Primary livestream URL (typically Twitch or YouTube). null if no stream has been announced.
+    #[serde(rename = "streamUrl")]
+    pub stream_url: Option<String>,
+    /// URL to the published event schedule. null if unavailable.
+    #[serde(rename = "scheduleUrl")]
+    pub schedule_url: Option<String>,
+    /// Absolute URL to the tournament's promotional banner image (wide aspect), self-hosted on meleemajors.gg.
+    #[serde(rename = "imageUrl")]
+    pub image_url: String,
+    /// Absolute URL to the tournament's square thumbnail/profile image (typically used as an icon), self-hosted on meleemajors.gg. null if start.gg does not expose a distinct profile image for this tournament.
+    #[serde(rename = "thumbnailUrl")]
+    pub thumbnail_url: Option<String>,
+    /// Most common color in the banner image as a lowercase hex color. Useful as a loading placeholder or accent color matching the event branding.
+    #[serde(rename = "imageColor")]
+    pub image_color: String,
+    /// Tiny blurred version of the banner image as a base64 WebP data: URI, for showing while imageUrl loads.
+    #[serde(rename = "imagePlaceholder")]
+    pub image_placeholder: String,
+    /// DEPRECATED: use `slug` instead. Retained for backwards compatibility and emits the same value. Internal slug-like identifier derived from the tournament's start.gg URL.
+    #[deprecated]
+    #[serde(rename = "startggTournamentName")]
+    pub startgg_tournament_name: String,
+    /// DEPRECATED: use `bracketUrl` instead. Retained for backwards compatibility and emits the same value. Canonical start.gg URL for the main Melee singles event page.
+    #[deprecated]
+    #[serde(rename = "startggUrl")]
+    pub startgg_url: String,
+    /// DEPRECATED: use `tournamentUrl` instead. Retained for backwards compatibility and emits the same value. start.gg URL for the tournament's overview /details page. null if the details URL cannot be derived from bracketUrl.
+    #[deprecated]
+    #[serde(rename = "startggDetailsUrl")]
+    pub startgg_details_url: Option<String>,
+}
@@ -0,0 +1,258 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::cli::PathArgs;
+
+/// Where the generator reads its inputs from and writes its outputs to.
+///
+/// Defaults to the repo's layout, found relative to the executable, so `cargo
+/// run` works from anywhere in the repo. Installed binaries, tests and extra
+/// sites set them in a build config file and/or on the command line instead,
+/// and installed binaries have to set all of them.
+#[derive(Clone, Debug)]
+pub struct Paths {
+    /// tournaments.json, topPlayers.json and config.json
+    pub data_dir: PathBuf,
+    /// Page and email templates in `html/`, start.gg queries in `graphql/`, and
+    /// the generated API types in `model/`
+    pub template_dir: PathBuf,
+    /// Card images and anything else kept between builds
+    pub cache_dir: PathBuf,
+    /// The hand-written parts of the site, like styles, assets and the API schema
+    pub static_dir: PathBuf,
+    /// Where the site is built. Starts as a copy of `static_dir`, unless they're
+    /// the same directory
+    pub out_dir: PathBuf,
+    /// Email and social post previews, and the local email outbox
+    pub preview_dir: PathBuf,
+}
+
+/// A build config file, e.g.
+///
+/// ```json
+/// { "data-dir": "data", "out-dir": "/var/www/meleemajors" }
+/// ```
+///
+/// Relative paths are relative to the file. Anything left out keeps its default.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct BuildConfig {
+    data_dir: Option<PathBuf>,
+    template_dir: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+    static_dir: Option<PathBuf>,
+    out_dir: Option<PathBuf>,
+    preview_dir: Option<PathBuf>,
+}
+
+impl BuildConfig {
+    fn apply(&mut self, overrides: BuildConfig, resolve: impl Fn(&Path) -> PathBuf) {
+        let fields = [
+            (&mut self.data_dir, overrides.data_dir),
+            (&mut self.template_dir, overrides.template_dir),
+            (&mut self.cache_dir, overrides.cache_dir),
+            (&mut self.static_dir, overrides.static_dir),
+            (&mut self.out_dir, overrides.out_dir),
+            (&mut self.preview_dir, overrides.preview_dir),
+        ];
+        for (dir, value) in fields {
+            if let Some(value) = value {
+                *dir = Some(resolve(&value));
+            }
+        }
+    }
+}
+
+/// The repo's `ssg/` and root directories, when the executable was built in
+/// the repo. With cargo run, it's in `ssg/target/debug`.
+fn repo_dirs() -> Result<(PathBuf, PathBuf)> {
+    let current_exe = std::env::current_exe().context("couldn't find the executable")?;
+    let ssg_dir = current_exe
+        .ancestors()
+        .nth(3)
+        .filter(|dir| dir.join("Cargo.toml").is_file())
+        .with_context(|| format!("{} wasn't built in the repo", current_exe.display()))?;
+    let repo_dir = ssg_dir.parent().context("the repo has no root")?;
+    Ok((ssg_dir.to_path_buf(), repo_dir.to_path_buf()))
+}
+
+/// `dir`, or where it is in the repo, failing with the flag that sets it.
+fn or_default(
+    dir: Option<PathBuf>,
+    flag: &str,
+    default: impl FnOnce(&Path, &Path) -> PathBuf,
+) -> Result<PathBuf> {
+    if let Some(dir) = dir {
+        return Ok(dir);
+    }
+    let (ssg_dir, repo_dir) = repo_dirs().with_context(|| format!("--{flag} isn't set"))?;
+    Ok(default(&ssg_dir, &repo_dir))
+}
+
+impl Paths {
+    /// The build config file, overridden by individual directories given on
+    /// the command line. Directories set in neither default to the repo's
+    /// layout, which is only looked for when one is needed.
+    pub fn from_args(args: &PathArgs) -> Result<Self> {
+        let mut dirs = BuildConfig::default();
+        if let Some(path) = &args.build_config {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let build_config: BuildConfig = serde_json::from_str(&contents)
+                .with_context(|| format!("invalid {}", path.display()))?;
+            let base = path.parent().unwrap_or(Path::new(""));
+            dirs.apply(build_config, |dir| base.join(dir));
+        }
+        let overrides = BuildConfig {
+            data_dir: args.data_dir.clone(),
+            template_dir: args.template_dir.clone(),
+            cache_dir: args.cache_dir.clone(),
+            static_dir: args.static_dir.clone(),
+            out_dir: args.out_dir.clone(),
+            preview_dir: args.preview_dir.clone(),
+        };
+        dirs.apply(overrides, Path::to_path_buf);
+
+        Ok(Self {
+            data_dir: or_default(dirs.data_dir, "data-dir", |ssg, _| ssg.join("src"))?,
+            template_dir: or_default(dirs.template_dir, "template-dir", |ssg, _| ssg.join("src"))?,
+            cache_dir: or_default(dirs.cache_dir, "cache-dir", |ssg, _| ssg.join("cache"))?,
+            static_dir: or_default(dirs.static_dir, "static-dir", |_, repo| repo.join("site"))?,
+            out_dir: or_default(dirs.out_dir, "out-dir", |_, repo| repo.join("site"))?,
+            preview_dir: or_default(dirs.preview_dir, "preview-dir", |_, repo| {
+                repo.to_path_buf()
+            })?,
+        })
+    }
+
+    /// e.g. `paths.data("tournaments.json")`
+    pub fn data(&self, file: &str) -> String {
+        join(&self.data_dir, file)
+    }
+
+    /// e.g. `paths.template("html/header.html")`
+    pub fn template(&self, file: &str) -> String {
+        join(&self.template_dir, file)
+    }
+
+    /// Reads a template, which is expected to exist.
+    pub fn read_template(&self, file: &str) -> String {
+        let path = self.template(file);
+        fs::read_to_string(&path).unwrap_or_else(|_| panic!("File not found: {path}"))
+    }
+
+    /// e.g. `paths.cache("cards")`
+    pub fn cache(&self, path: &str) -> String {
+        join(&self.cache_dir, path)
+    }
+
+    /// A hand-written site file, e.g. `paths.static_file("assets/defaultCard.webp")`.
+    /// A leading `/` is ignored, so site-relative URLs work too.
+    pub fn static_file(&self, path: &str) -> String {
+        join(&self.static_dir, path)
+    }
+
+    /// A built site file, e.g. `paths.site("index.html")`. A leading `/` is
+    /// ignored, so site-relative URLs work too.
+    pub fn site(&self, path: &str) -> String {
+        join(&self.out_dir, path)
+    }
+
+    /// e.g. `paths.preview("email-previews")`
+    pub fn preview(&self, path: &str) -> String {
+        join(&self.preview_dir, path)
+    }
+}
+
+#[cfg(test)]
+impl Paths {
+    /// Reads and writes everything in `dir`, except templates and static files,
+    /// which come from the repo.
+    pub(crate) fn for_test(dir: &Path) -> Self {
+        let ssg_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        Self {
+            data_dir: dir.to_path_buf(),
+            template_dir: ssg_dir.join("src"),
+            cache_dir: dir.join("cache"),
+            static_dir: ssg_dir.join("../site"),
+            out_dir: dir.join("site"),
+            preview_dir: dir.to_path_buf(),
+        }
+    }
+}
+
+fn join(dir: &Path, path: &str) -> String {
+    dir.join(path.trim_start_matches('/'))
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(build_config: Option<PathBuf>) -> PathArgs {
+        PathArgs {
+            build_config,
+            data_dir: None,
+            template_dir: None,
+            cache_dir: None,
+            static_dir: None,
+            out_dir: None,
+            preview_dir: None,
+        }
+    }
+
+    #[test]
+    fn flags_override_the_build_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let build_config = dir.path().join("build.json");
+        fs::write(
+            &build_config,
+            r#"{
+                "data-dir": "data",
+                "template-dir": "templates",
+                "cache-dir": "/var/cache/meleemajors",
+                "static-dir": "static",
+                "out-dir": "site",
+                "preview-dir": "previews"
+            }"#,
+        )
+        .unwrap();
+        let out_dir = dir.path().join("elsewhere");
+
+        let paths = Paths::from_args(&PathArgs {
+            out_dir: Some(out_dir.clone()),
+            ..args(Some(build_config))
+        })
+        .unwrap();
+        assert_eq!(paths.data_dir, dir.path().join("data"));
+        assert_eq!(paths.template_dir, dir.path().join("templates"));
+        assert_eq!(paths.cache_dir, Path::new("/var/cache/meleemajors"));
+        assert_eq!(paths.static_dir, dir.path().join("static"));
+        assert_eq!(paths.out_dir, out_dir);
+        assert_eq!(paths.preview_dir, dir.path().join("previews"));
+        assert_eq!(
+            paths.site("/api/v1/tournaments.json"),
+            out_dir.join("api/v1/tournaments.json").to_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_directories_are_named_outside_the_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let build_config = dir.path().join("build.json");
+        fs::write(&build_config, r#"{ "data-dir": "data" }"#).unwrap();
+
+        // Test binaries are in target/debug/deps, so there's no repo to default to
+        let error = Paths::from_args(&args(Some(build_config))).unwrap_err();
+        assert!(
+            format!("{error:#}").starts_with("--template-dir isn't set: "),
+            "{error:#}"
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use chrono::{DateTime, Utc};
+use icalendar::Calendar;
+use serde_json::{json, Value};
+
+use crate::api::{tournament_to_api, validate_api_payload};
+use crate::calendar::generate_calendar;
+use crate::paths::Paths;
+use crate::utils::{log_success, log_warn, replace_placeholder_values, slug};
+
+/// Inverts the scraped tournament data into a per-player index: for every
+/// ranked player in topPlayers.json, which upcoming majors they're registered for.
+///
+/// Writes `/api/v1/players.json`, one `/api/v1/players/<slug>.json` per player,
+/// and one `/players/<slug>.html` page and `/players/<slug>.ics` calendar per
+/// player, plus a `/players/` page listing them all.
+pub fn make_players(
+    schema: &Value,
+    tournaments: &[Value],
+    top_players: &Value,
+    last_updated: &DateTime<Utc>,
+    paths: &Paths,
+) {
+    let resources_path = paths.site("api/v1/players");
+    let pages_path = paths.site("players");
+    fs::create_dir_all(&resources_path).unwrap();
+    fs::create_dir_all(&pages_path).unwrap();
+    let template_player = paths.read_template("html/templatePlayer.html");
+    let template_tournament = paths.read_template("html/templatePlayerTournament.html");
+    let template_entry = paths.read_template("html/templatePlayersEntry.html");
+
+    let mut sorted = tournaments.to_vec();
+    sorted.sort_by_key(|t| t["start-unix-timestamp"].as_i64().unwrap_or(i64::MAX));
+
+    let names: Vec<&str> = top_players
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|player| player.as_str())
+        .collect();
+
+    let mut players: Vec<Value> = Vec::new();
+    let mut entries_html = String::new();
+    let mut written_files: HashSet<String> = HashSet::new();
+    for (i, (name, slug)) in names.iter().zip(player_slugs(&names)).enumerate() {
+        let name = *name;
+        let appearances: Vec<&Value> = sorted
+            .iter()
+            .filter(|tournament| is_registered(tournament, name))
+            .collect();
+
+        let player = json!({
+            "name": name,
+            "slug": slug,
+            "rank": i + 1,
+            "url": format!("https://meleemajors.gg/api/v1/players/{slug}.json"),
+            "calendarUrl": format!("https://meleemajors.gg/players/{slug}.ics"),
+            "tournaments": appearances
+                .iter()
+                .map(|tournament| player_appearance(tournament))
+                .collect::<Vec<Value>>(),
+        });
+        validate_api_payload(schema, "#/$defs/player", &player);
+
+        fs::write(
+            format!("{resources_path}/{slug}.json"),
+            serde_json::to_string_pretty(&player).unwrap(),
+        )
+        .unwrap();
+
+        let mut calendar = Calendar::new()
+            .name(&format!("{name} at upcoming melee majors"))
+            .done();
+        for tournament in &appearances {
+            calendar = generate_calendar((*tournament).clone(), &mut calendar, last_updated);
+        }
+        fs::write(format!("{pages_path}/{slug}.ics"), calendar.to_string()).unwrap();
+
+        let page_data = json!({
+            "name": name,
+            "slug": slug,
+            "rank": i + 1,
+            "tournament-count": tournament_count(appearances.len()),
+            "calendar-url": format!("/players/{slug}.ics"),
+            "api-url": format!("/api/v1/players/{slug}.json"),
+            "player-tournaments": appearances
+                .iter()
+                .map(|tournament| replace_placeholder_values(tournament, &template_tournament))
+                .collect::<String>(),
+        });
+        fs::write(
+            format!("{pages_path}/{slug}.html"),
+            replace_placeholder_values(&page_data, &template_player),
+        )
+        .unwrap();
+        entries_html.push_str(&replace_placeholder_values(&page_data, &template_entry));
+
+        written_files.insert(format!("{slug}.json"));
+        written_files.insert(format!("{slug}.ics"));
+        written_files.insert(format!("{slug}.html"));
+        players.push(player);
+    }
+
+    let index_data = json!({ "player-count": names.len(), "players": entries_html });
+    fs::write(
+        format!("{pages_path}/index.html"),
+        replace_placeholder_values(
+            &index_data,
+            &paths.read_template("html/templatePlayers.html"),
+        ),
+    )
+    .unwrap();
+    written_files.insert("index.html".to_string());
+
+    // Remove files for players that dropped out of topPlayers.json
+    for dir in [&resources_path, &pages_path] {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let file_name = path.file_name().unwrap().to_str().unwrap().to_string();
+            if !written_files.contains(&file_name) {
+                fs::remove_file(path).ok();
+            }
+        }
+    }
+
+    let payload = json!({
+        "lastUpdated": last_updated.to_rfc3339(),
+        "players": players,
+    });
+    validate_api_payload(schema, "#/$defs/players", &payload);
+    fs::write(
+        paths.site("api/v1/players.json"),
+        serde_json::to_string_pretty(&payload).unwrap(),
+    )
+    .unwrap();
+
+    let appearing = players
+        .iter()
+        .filter(|player| !player["tournaments"].as_array().unwrap().is_empty())
+        .count();
+    log_success(
+        "players",
+        &format!(
+            "wrote /api/v1/players.json ({} players, {appearing} registered for a major)",
+            players.len()
+        ),
+    );
+}
+
+/// e.g. "2 upcoming majors", for the player pages.
+fn tournament_count(count: usize) -> String {
+    match count {
+        0 => "no upcoming majors".to_string(),
+        1 => "1 upcoming major".to_string(),
+        n => format!("{n} upcoming majors"),
+    }
+}
+
+/// Registered according to start.gg, or manually featured with a `playerN` override.
+fn is_registered(tournament: &Value, name: &str) -> bool {
+    let registered = tournament["registered-players"]
+        .as_array()
+        .is_some_and(|players| players.iter().any(|player| player == name));
+    let featured = (0..8).any(|i| tournament[format!("player{i}")] == name);
+    registered || featured
+}
+
+fn player_appearance(tournament: &Value) -> Value {
+    let api = tournament_to_api(tournament);
+    json!({
+        "slug": api.slug,
+        "name": api.name,
+        "startTimestamp": api.start_timestamp,
+        "endTimestamp": api.end_timestamp,
+        "dateString": api.date_string,
+        "cityAndState": api.city_and_state,
+        "bracketUrl": api.bracket_url,
+        "url": format!("https://meleemajors.gg/api/v1/tournaments/{}.json", api.slug),
+    })
+}
+
+/// A unique slug for each player, in the same order. When two tags share a
+/// slug (e.g. `Ken` and `KEN`), the higher-ranked player keeps
+/// it and the other gets a `-2`, `-3`... suffix, so neither overwrites the
+/// other's files.
+fn player_slugs(names: &[&str]) -> Vec<String> {
+    let mut taken: HashMap<String, &str> = HashMap::new();
+    names
+        .iter()
+        .map(|name| {
+            let base = slug(name);
+            let mut slug = base.clone();
+            let mut n = 2;
+            while let Some(other) = taken.get(&slug) {
+                log_warn(
+                    "players",
+                    &format!("{name} and {other} both have the slug {slug}"),
+                );
+                slug = format!("{base}-{n}");
+                n += 1;
+            }
+            taken.insert(slug.clone(), name);
+            slug
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tournament(slug: &str, name: &str, players: &[&str]) -> Value {
+        json!({
+            "start.gg-tournament-name": slug,
+            "name": name,
+            "date": "April 10 - April 12",
+            "start-unix-timestamp": 1775826000,
+            "end-unix-timestamp": 1776034800,
+            "timezone": "America/Los_Angeles",
+            "city-and-state": "San Diego, CA",
+            "full-address": "Price Center, La Jolla, CA 92093, USA",
+            "maps-link": "https://www.google.com/maps/search/?api=1&query=Price%20Center",
+            "start.gg-url": format!("https://www.start.gg/tournament/{slug}/event/melee-singles"),
+            "registered-players": players,
+        })
+    }
+
+    #[test]
+    fn pages_list_each_players_majors() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::for_test(dir.path());
+        let schema = crate::api::read_api_schema(&paths);
+        let tournaments = [
+            tournament("sapf2", "SAPF 2", &["Zain"]),
+            tournament("genesis", "Genesis", &["Zain", "Cody Schwab"]),
+        ];
+
+        make_players(
+            &schema,
+            &tournaments,
+            &json!(["Zain", "Cody Schwab", "Mango"]),
+            &Utc::now(),
+            &paths,
+        );
+
+        let read = |file: &str| fs::read_to_string(paths.site(file)).unwrap();
+        let zain = read("players/zain.html");
+        assert!(zain.contains("Rank 1 • 2 upcoming majors"));
+        assert!(zain.contains("SAPF 2") && zain.contains("Genesis"));
+        assert!(zain.contains(r#"href="/players/zain.ics""#));
+        assert!(!zain.contains("{{"));
+        assert!(!read("players/cody-schwab.html").contains("SAPF 2"));
+        assert!(read("players/mango.html").contains("no upcoming majors"));
+
+        let index = read("players/index.html");
+        assert!(index.contains("3 ranked players"));
+        for slug in ["zain", "cody-schwab", "mango"] {
+            assert!(index.contains(&format!(r#"href="/players/{slug}.html""#)));
+        }
+        assert!(!index.contains("{{"));
+    }
+
+    #[test]
+    fn colliding_slugs_are_disambiguated() {
+        assert_eq!(
+            player_slugs(&["Ken", "KEN", "kén", "Ken 2"]),
+            ["ken", "ken-2", "ken-3", "ken-2-2"]
+        );
+    }
+}
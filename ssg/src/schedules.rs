@@ -0,0 +1,45 @@
+use std::collections::HashSet;
+use std::fs;
+
+use serde_json::Value;
+
+use crate::paths::Paths;
+use crate::utils::{log_success, replace_placeholder_values};
+
+/// Writes a `/schedules/<slug>.html` viewer page for every tournament whose
+/// `schedule-url` is a local image, and removes pages for the rest.
+pub fn make_schedule_pages(tournaments: &[Value], paths: &Paths) {
+    let schedules_path = paths.site("schedules");
+    fs::create_dir_all(&schedules_path).unwrap();
+    let template = paths.read_template("html/templateSchedule.html");
+
+    let mut written_files: HashSet<String> = HashSet::new();
+    for tournament in tournaments {
+        if tournament["schedule-image-url"].is_null() {
+            continue;
+        }
+        let file_name = format!(
+            "{}.html",
+            tournament["start.gg-tournament-name"].as_str().unwrap()
+        );
+        fs::write(
+            format!("{schedules_path}/{file_name}"),
+            replace_placeholder_values(tournament, &template),
+        )
+        .unwrap();
+        written_files.insert(file_name);
+    }
+
+    for entry in fs::read_dir(&schedules_path).unwrap() {
+        let path = entry.unwrap().path();
+        let file_name = path.file_name().unwrap().to_str().unwrap().to_string();
+        if !written_files.contains(&file_name) {
+            fs::remove_file(path).ok();
+        }
+    }
+
+    log_success(
+        "schedules",
+        &format!("wrote {} schedule pages", written_files.len()),
+    );
+}
@@ -0,0 +1,484 @@
+use std::fs;
+
+use case_converter::kebab_to_camel;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use itertools::Itertools;
+use regex::Regex;
+use serde_json::{json, Value};
+use urlencoding::encode;
+
+use crate::cli::{InputArgs, ScrapeArgs};
+use crate::images;
+use crate::paths::Paths;
+use crate::startgg::StartggClient;
+use crate::utils::{log_error, log_grey, log_heading, log_red, log_success, log_warn};
+
+/// Scrapes every tournament in tournaments.json, and returns the ones that
+/// haven't ended, soonest first, along with topPlayers.json.
+///
+/// With a cached `startgg` client, tournaments that haven't been scraped by
+/// an earlier build are skipped rather than failing the whole render.
+pub async fn scrape_tournaments(
+    inputs: &InputArgs,
+    startgg: &mut StartggClient,
+    paths: &Paths,
+) -> Result<(Vec<Value>, Value), String> {
+    let queries = Queries::load(paths);
+    let top_players_path = inputs.top_players_path(paths);
+    let json_featured_players: Value = serde_json::from_str(
+        &fs::read_to_string(&top_players_path)
+            .unwrap_or_else(|e| panic!("failed to read {top_players_path}: {e}")),
+    )
+    .unwrap();
+    let tournaments_path = inputs.tournaments_path(paths);
+    let tournaments = fs::read_to_string(&tournaments_path)
+        .unwrap_or_else(|e| panic!("failed to read {tournaments_path}: {e}"));
+    let json_tournaments: Value = serde_json::from_str(&tournaments).unwrap();
+    let mut image_cache = images::ImageCache::load(paths, startgg.is_offline());
+
+    // scrape all tournament data
+    let mut all_tournament_data: Vec<Value> = Vec::new();
+    match json_tournaments {
+        Value::Array(vec) => {
+            for tournament in vec.iter() {
+                match scrape_data(
+                    tournament,
+                    startgg,
+                    &queries,
+                    &json_featured_players,
+                    &mut image_cache,
+                    paths,
+                )
+                .await
+                {
+                    Ok(tournament_data) => all_tournament_data.push(tournament_data),
+                    Err(e) if startgg.is_offline() => {
+                        let url = tournament["bracketUrl"].as_str().unwrap_or("unknown URL");
+                        log_warn("start.gg", &format!("skipping {url}"));
+                        log_grey(&e);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        _ => panic!("root must be an array"),
+    }
+    image_cache
+        .save()
+        .unwrap_or_else(|e| panic!("failed to save image manifest: {e:#}"));
+    if !startgg.is_offline() {
+        startgg.prune();
+    }
+
+    log_heading("Preparing output");
+
+    // filter out past tournaments and sort by date (soonest first)
+    let now = Utc::now().timestamp();
+    let mut upcoming_tournament_data: Vec<Value> = Vec::new();
+    for tournament_data in all_tournament_data {
+        match tournament_data["end-unix-timestamp"].as_i64() {
+            Some(end) if end <= now => {
+                let url = tournament_data["start.gg-url"]
+                    .as_str()
+                    .unwrap_or("unknown URL");
+                let ended = unix_timestamp_to_log_date(end);
+                log_warn("data", &format!("omitting {url} ended {ended}"));
+            }
+            _ => upcoming_tournament_data.push(tournament_data),
+        }
+    }
+    upcoming_tournament_data
+        .sort_by_key(|t| t["start-unix-timestamp"].as_i64().unwrap_or(i64::MAX));
+    log_success(
+        "data",
+        &format!("{} upcoming tournaments", upcoming_tournament_data.len()),
+    );
+    Ok((upcoming_tournament_data, json_featured_players))
+}
+
+/// Scrapes start.gg live, for a build that goes on to send notifications.
+pub async fn scrape_live(args: &ScrapeArgs, paths: &Paths) -> (Vec<Value>, Value) {
+    let mut startgg = StartggClient::new(&args.startgg_token, paths);
+    scrape_tournaments(&args.inputs, &mut startgg, paths)
+        .await
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// The start.gg GraphQL queries in `graphql/`.
+pub struct Queries {
+    pub tournament_info: String,
+    pub tournament_entrants: String,
+    pub featured_players: String,
+}
+
+impl Queries {
+    pub fn load(paths: &Paths) -> Self {
+        Self {
+            tournament_info: paths.read_template("graphql/getTournamentInfo.gql"),
+            tournament_entrants: paths.read_template("graphql/getTournamentEntrants.gql"),
+            featured_players: paths.read_template("graphql/getFeaturedPlayers.gql"),
+        }
+    }
+}
+
+/// Scrapes one tournament from tournaments.json, downloading its images,
+/// and applies its overrides.
+pub async fn scrape_data(
+    tournament: &Value,
+    startgg: &mut StartggClient,
+    queries: &Queries,
+    featured_players_json: &Value,
+    image_cache: &mut images::ImageCache,
+    paths: &Paths,
+) -> Result<Value, String> {
+    let melee_singles_url = tournament["bracketUrl"].as_str().unwrap();
+    let event_slug = Regex::new(r"^(https?://)?(www\.)?start\.gg/")
+        .unwrap()
+        .replace(melee_singles_url, "");
+
+    let event_slug_parts: Vec<&str> = event_slug.split('/').collect();
+    let tournament_slug = event_slug_parts.get(1).unwrap_or(&"").to_string();
+    let tournament_info_vars = json!({
+      "slug": tournament_slug,
+      "slug_event": event_slug
+    });
+
+    let result_tournament_info = startgg
+        .query(
+            "tournamentInfo",
+            &queries.tournament_info,
+            tournament_info_vars.clone(),
+        )
+        .await?;
+
+    let tournament_info = &result_tournament_info["tournament"];
+    // println!("{tournament_info}");
+
+    let name = tournament_info["name"].as_str().unwrap(); // ---> result
+
+    log_heading(name);
+    log_success("start.gg", "scraped tournaments");
+
+    let tournament_entrants_var = json!({
+      "eventId": result_tournament_info["event"].get("id").unwrap().to_string(),
+    });
+    let result_entrant_count = startgg
+        .query(
+            "tournamentEntrants",
+            &queries.tournament_entrants,
+            tournament_entrants_var,
+        )
+        .await?;
+    log_success("start.gg", "scraped entrants");
+
+    let featured_players_vars = json!({
+        "slug_event": event_slug
+    });
+    let result_featured_players = startgg
+        .query(
+            "featuredPlayers",
+            &queries.featured_players,
+            featured_players_vars,
+        )
+        .await?
+        .to_string();
+    log_success("start.gg", "scraped top 8 players");
+
+    // Every ranked player registered for the event, in ranking order
+    let registered_players: Vec<String> = featured_players_json
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|player| {
+            result_featured_players.contains(&(player.as_str().unwrap().to_owned() + "\""))
+        })
+        .map(|player| player.as_str().unwrap().to_string())
+        .collect();
+
+    let featured_players_top_eight: Vec<Option<String>> = registered_players
+        .iter()
+        .take(8)
+        .map(|player| Some(player.clone()))
+        .pad_using(8, |_| None)
+        .collect();
+
+    let entrant_count = result_entrant_count["event"]["numEntrants"].as_number();
+    let entrant_count_string = match entrant_count {
+        Some(entrant_count) => entrant_count.to_string(),
+        None => "TBD".to_string(),
+    }; // ---> result
+
+    let timezone: Tz = tournament_info["timezone"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .expect("Invalid timezone");
+
+    let start_date = unix_timestamp_to_readable_date(&tournament_info["startAt"], timezone);
+
+    let end_date = unix_timestamp_to_readable_date(&tournament_info["endAt"], timezone);
+
+    let date = format!("{start_date} - {end_date}"); // ---> result
+
+    // let city = tournament_info["city"].as_str().unwrap();
+    let city = tournament_info["city"].as_str().unwrap_or("Unknown");
+    let state = tournament_info["addrState"].as_str().unwrap();
+
+    let city_and_state = format!("{}, {}", city, state); // ---> result
+
+    // e.g. "US-CA", or just "GB" outside of the US and Canada. Used to pick
+    // which region's subscribers get emails about the tournament
+    let country = tournament_info["countryCode"].as_str().unwrap_or("");
+    let location_code = match (country, state) {
+        ("US" | "CA", state) if !state.is_empty() => format!("{country}-{state}"),
+        _ => country.to_string(),
+    };
+
+    let address = tournament_info["venueAddress"].as_str().unwrap();
+
+    let banner_images = tournament_info["images"].as_array().unwrap();
+    let strip_query = Regex::new(r"\?.*").unwrap();
+
+    // start.gg's Image.type is a free-form String with no documented enum. Empirically we see
+    // "banner" (wide) and "profile" (square icon). Prefer matching the explicit type, and fall
+    // back to max/min width so a tournament with unexpected type values still gets an image.
+    let banner_image = banner_images
+        .iter()
+        .find(|img| img["type"].as_str() == Some("banner"))
+        .or_else(|| {
+            banner_images
+                .iter()
+                .max_by_key(|img| img["width"].as_u64().unwrap_or(0))
+        })
+        .unwrap();
+    let banner_url = strip_query.replace(banner_image["url"].as_str().unwrap(), "");
+
+    let profile_image = banner_images
+        .iter()
+        .find(|img| img["type"].as_str() == Some("profile"))
+        .or_else(|| {
+            // Only fall back to a second distinct image — if there's just one, it's the banner.
+            if banner_images.len() > 1 {
+                banner_images
+                    .iter()
+                    .min_by_key(|img| img["width"].as_u64().unwrap_or(u64::MAX))
+            } else {
+                None
+            }
+        });
+
+    let name_camel = kebab_to_camel(&tournament_slug);
+
+    // `image-url` and `thumbnail-url` in tournaments.json replace start.gg's images,
+    // and `image-crop`/`image-focal-point` (or `thumbnail-`) fix up badly cropped ones
+    let banner_source = match tournament["image-url"].as_str() {
+        Some(image_override) => images::ImageSource::from_override(image_override),
+        None => Ok(images::ImageSource::Remote(banner_url.to_string())),
+    };
+    let banner = async {
+        let hints =
+            images::ImageHints::from_overrides(tournament, "image", images::CARD_ASPECT_RATIO)?;
+        images::download_tournament_image(&banner_source?, &name_camel, &hints, image_cache, paths)
+            .await
+    }
+    .await;
+    let banner = match banner {
+        Ok(banner) => banner,
+        Err(e) => {
+            log_error("image", &format!("banner failed for {name}, using default"));
+            log_red(&format!("{e:#}"));
+            images::CardImage::default_card(paths)
+        }
+    };
+    let mut image_files = banner.files.clone();
+
+    let thumbnail_source = match tournament["thumbnail-url"].as_str() {
+        Some(thumbnail_override) => Some(images::ImageSource::from_override(thumbnail_override)),
+        None => profile_image.map(|img| {
+            let url = strip_query.replace(img["url"].as_str().unwrap(), "");
+            Ok(images::ImageSource::Remote(url.to_string()))
+        }),
+    };
+    let mut thumbnail_url = None;
+    if let Some(thumbnail_source) = thumbnail_source {
+        let thumb_name = format!("{name_camel}.thumbnail");
+        let thumbnail = async {
+            let hints = images::ImageHints::from_overrides(tournament, "thumbnail", 1.0)?;
+            images::download_tournament_image(
+                &thumbnail_source?,
+                &thumb_name,
+                &hints,
+                image_cache,
+                paths,
+            )
+            .await
+        }
+        .await;
+        match thumbnail {
+            Ok(thumbnail) => {
+                thumbnail_url = Some(thumbnail.url);
+                image_files.extend(thumbnail.files);
+            }
+            Err(e) => {
+                log_error("image", &format!("thumbnail failed for {name}"));
+                log_red(&format!("{e:#}"));
+            }
+        }
+    }
+
+    let stream_url = resolve_stream_url(tournament, &tournament_info["streams"]);
+    let mut schedule_url = tournament["schedule-url"]
+        .as_str()
+        .unwrap_or("")
+        .to_string();
+
+    // A `schedule-url` that isn't a link is an image under site/, which gets
+    // converted to WebP and shown on its own /schedules/ page
+    let mut schedule_image = None;
+    if !schedule_url.is_empty() && !schedule_url.starts_with("http") {
+        let image = images::convert_schedule_image(&schedule_url, &name_camel, paths)
+            .map_err(|e| format!("bad schedule-url for {name}: {e:#}"))?;
+        image_files.push(image.file.clone());
+        schedule_url = format!("/schedules/{name_camel}.html");
+        schedule_image = Some(image);
+    }
+
+    let stream_link_class = if stream_url.is_empty() { " hidden" } else { "" };
+    // An empty `url('')` would make browsers fetch the page itself as an image
+    let image_placeholder_style = if banner.placeholder.is_empty() {
+        String::new()
+    } else {
+        format!(" style=\"background-image: url('{}')\"", banner.placeholder)
+    };
+
+    Ok(json!({
+        "start.gg-tournament-name": name_camel,
+        "image-url": banner.url,
+        "image-width": banner.width,
+        "image-height": banner.height,
+        "image-srcset-webp": banner.srcset_webp,
+        "image-srcset-avif": banner.srcset_avif,
+        "image-color": banner.color,
+        "image-placeholder": banner.placeholder,
+        "image-placeholder-style": image_placeholder_style,
+        "image-url-thumbnail": thumbnail_url,
+        "image-files": image_files,
+        "name": check_override(tournament, name.to_string(), "name"),
+        "date": date,
+        "start-unix-timestamp": tournament_info["startAt"],
+        "end-unix-timestamp": tournament_info["endAt"],
+        "registration-closes-unix-timestamp": tournament_info["registrationClosesAt"],
+        "timezone": tournament_info["timezone"],
+        "player0": check_override_nullable(tournament, featured_players_top_eight[0].clone(), "player0"),
+        "player1": check_override_nullable(tournament, featured_players_top_eight[1].clone(), "player1"),
+        "player2": check_override_nullable(tournament, featured_players_top_eight[2].clone(), "player2"),
+        "player3": check_override_nullable(tournament, featured_players_top_eight[3].clone(), "player3"),
+        "player4": check_override_nullable(tournament, featured_players_top_eight[4].clone(), "player4"),
+        "player5": check_override_nullable(tournament, featured_players_top_eight[5].clone(), "player5"),
+        "player6": check_override_nullable(tournament, featured_players_top_eight[6].clone(), "player6"),
+        "player7": check_override_nullable(tournament, featured_players_top_eight[7].clone(), "player7"),
+        "registered-players": registered_players,
+        "entrants": entrant_count_string,
+        "city-and-state": check_override(tournament, city_and_state.to_string(), "city-and-state"),
+        "maps-link": check_override(
+          tournament,
+          format!("https://www.google.com/maps/search/?api=1&query={}", encode(address)),
+          "maps-link"
+        ),
+        "full-address": address,
+        "location-code": check_override(tournament, location_code, "location-code"),
+        "start.gg-url": melee_singles_url,
+        "stream-url": stream_url,
+        "schedule-url": schedule_url,
+        "schedule-link-class": if schedule_url.is_empty() {" hidden"} else {""},
+        "schedule-image-url": schedule_image.as_ref().map(|image| &image.url),
+        "schedule-image-width": schedule_image.as_ref().map(|image| image.width),
+        "schedule-image-height": schedule_image.as_ref().map(|image| image.height),
+        "stream-link-class": stream_link_class,
+        "top8-start-time": tournament["top8-start-time"],
+        "notifications": tournament["notifications"],
+    }))
+}
+
+// Manual `stream-url` override in tournaments.json wins when set to a non-empty
+// string; otherwise the first stream returned by start.gg is used.
+fn resolve_stream_url(tournament: &Value, streams: &Value) -> String {
+    if let Some(override_url) = tournament.get("stream-url").and_then(|v| v.as_str()) {
+        if !override_url.is_empty() {
+            return override_url.to_string();
+        }
+    }
+
+    streams
+        .as_array()
+        .and_then(|arr| arr.iter().find_map(stream_to_url))
+        .unwrap_or_default()
+}
+
+fn stream_to_url(stream: &Value) -> Option<String> {
+    let name = stream.get("streamName").and_then(|v| v.as_str())?;
+    if name.is_empty() {
+        return None;
+    }
+    match stream.get("streamSource").and_then(|v| v.as_str()) {
+        Some("TWITCH") => Some(format!("https://www.twitch.tv/{name}")),
+        Some("YOUTUBE") => Some(format!("https://www.youtube.com/{name}")),
+        Some(other @ ("HITBOX" | "STREAMME" | "MIXER")) => {
+            log_warn(
+                "start.gg",
+                &format!("deprecated streamSource {other:?} for stream {name:?}; ignoring"),
+            );
+            None
+        }
+        Some(other) => {
+            log_warn(
+                "start.gg",
+                &format!("unrecognized streamSource {other:?} for stream {name:?}; ignoring"),
+            );
+            None
+        }
+        None => {
+            log_warn(
+                "start.gg",
+                &format!("missing streamSource for stream {name:?}; ignoring"),
+            );
+            None
+        }
+    }
+}
+
+fn check_override(tournament: &Value, default_value: String, default_key: &str) -> String {
+    let obj_tournament = tournament.as_object().unwrap();
+    if obj_tournament.contains_key(default_key) {
+        return obj_tournament[default_key].as_str().unwrap().to_string();
+    }
+    default_value
+}
+
+fn check_override_nullable(
+    tournament: &Value,
+    default_value: Option<String>,
+    default_key: &str,
+) -> Option<String> {
+    tournament
+        .as_object()
+        .and_then(|obj| obj.get(default_key))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .or(default_value)
+}
+
+fn unix_timestamp_to_readable_date(date: &Value, timezone: Tz) -> String {
+    DateTime::from_timestamp(date.as_i64().unwrap(), 0)
+        .unwrap()
+        .with_timezone(&timezone)
+        .format("%B %d")
+        .to_string()
+}
+
+fn unix_timestamp_to_log_date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|date| date.format("%B %-d").to_string())
+        .unwrap_or_else(|| format!("invalid timestamp {timestamp}"))
+}
@@ -0,0 +1,261 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use tiny_http::{Header, Response, Server};
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+
+use crate::cli::{InputArgs, ServeArgs};
+use crate::paths::Paths;
+use crate::startgg::StartggClient;
+use crate::utils::{log_error, log_green, log_grey, log_heading, log_success, log_warn};
+
+/// Polled by every served page, which reloads when the site has been rebuilt.
+const LIVE_RELOAD_URL: &str = "/__livereload";
+
+const LIVE_RELOAD_SCRIPT: &str = r#"<script>
+  (async () => {
+    let version;
+    for (;;) {
+      try {
+        const latest = await (await fetch("/__livereload")).text();
+        if (version !== undefined && latest !== version) location.reload();
+        version = latest;
+      } catch {}
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+    }
+  })();
+</script>
+"#;
+
+/// Serves the site at http://localhost:<port>, until it's killed.
+///
+/// The site is rendered from the start.gg responses cached by the last build,
+/// then re-rendered whenever the templates, tournaments.json, topPlayers.json
+/// or config.json change, so nothing here needs a token or the network.
+pub async fn main(args: ServeArgs, paths: &Paths) {
+    crate::site::prepare_out_dir(paths);
+    let version = Arc::new(AtomicU64::new(0));
+    rebuild(&args.inputs, paths, &version).await;
+
+    let server = Server::http(("127.0.0.1", args.port))
+        .unwrap_or_else(|e| panic!("failed to listen on port {}: {e}", args.port));
+    let root = paths.out_dir.clone();
+    let url = format!("http://localhost:{}", args.port);
+    log_green(&format!("\nServing {} at {url}", root.display()));
+    log_grey("Press Ctrl+C to stop");
+    let server_version = version.clone();
+    std::thread::spawn(move || serve_files(server, &root, &server_version));
+
+    if !args.no_open {
+        if let Err(e) = webbrowser::open(&url) {
+            log_warn("browser", &format!("Failed to open browser: {e}"));
+        }
+    }
+
+    let (sender, mut events) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        sender.send(event).ok();
+    })
+    .unwrap();
+    let watched = WatchedFiles::new(&args.inputs, paths);
+    for (dir, mode) in watched.dirs() {
+        if let Err(e) = watcher.watch(&dir, mode) {
+            log_warn("serve", &format!("not watching {}: {e}", dir.display()));
+        }
+    }
+
+    while let Some(event) = events.recv().await {
+        let mut changed = watched.changed_paths(event);
+        // Editors often save in a few steps, so wait for them to finish
+        sleep(Duration::from_millis(200)).await;
+        while let Ok(event) = events.try_recv() {
+            changed.extend(watched.changed_paths(event));
+        }
+        let Some(path) = changed.first() else {
+            continue;
+        };
+        log_heading(&format!("{} changed", path.display()));
+        if changed
+            .iter()
+            .any(|path| path.starts_with(&watched.static_dir))
+        {
+            crate::site::prepare_out_dir(paths);
+        }
+        rebuild(&args.inputs, paths, &version).await;
+    }
+}
+
+/// Re-renders the site from cached start.gg responses, and tells open pages
+/// to reload if it worked.
+///
+/// Runs on its own task, so a panic (say, from tournaments.json being saved
+/// halfway through an edit) is reported without stopping the server.
+async fn rebuild(inputs: &InputArgs, paths: &Paths, version: &AtomicU64) {
+    let (inputs, paths) = (inputs.clone(), paths.clone());
+    let rebuilt = tokio::spawn(async move {
+        let config = inputs.load_config(&paths)?;
+        let mut startgg = StartggClient::cached(&paths);
+        let (tournaments, top_players) =
+            crate::scrape::scrape_tournaments(&inputs, &mut startgg, &paths)
+                .await
+                .map_err(anyhow::Error::msg)?;
+        crate::site::render_site(&tournaments, &top_players, &config, &paths);
+        anyhow::Ok(())
+    })
+    .await;
+    match rebuilt {
+        Ok(Ok(())) => {
+            version.fetch_add(1, Ordering::SeqCst);
+            log_success("serve", "site rebuilt");
+        }
+        Ok(Err(e)) => log_error("serve", &format!("rebuild failed: {e:#}")),
+        Err(_) => log_error("serve", "rebuild failed, see the error above"),
+    }
+}
+
+/// The templates and input files that trigger a rebuild when they change.
+struct WatchedFiles {
+    templates: Vec<PathBuf>,
+    inputs: Vec<PathBuf>,
+    /// Watched too when it's separate from the output directory, to copy
+    /// changes over. Otherwise builds would trigger themselves
+    static_dir: PathBuf,
+    watch_static_dir: bool,
+}
+
+impl WatchedFiles {
+    fn new(inputs: &InputArgs, paths: &Paths) -> Self {
+        let absolute = |path: &str| std::path::absolute(path).unwrap();
+        Self {
+            templates: vec![
+                absolute(&paths.template("html")),
+                absolute(&paths.template("graphql")),
+            ],
+            inputs: vec![
+                absolute(&inputs.tournaments_path(paths)),
+                absolute(&inputs.top_players_path(paths)),
+                absolute(&inputs.config_path(paths)),
+            ],
+            static_dir: std::path::absolute(&paths.static_dir).unwrap(),
+            watch_static_dir: fs::canonicalize(&paths.static_dir).ok()
+                != fs::canonicalize(&paths.out_dir).ok(),
+        }
+    }
+
+    /// Input files are watched through their directories, since editors often
+    /// save by replacing the file.
+    fn dirs(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        let mut dirs: Vec<(PathBuf, RecursiveMode)> = self
+            .templates
+            .iter()
+            .map(|dir| (dir.clone(), RecursiveMode::Recursive))
+            .collect();
+        for file in &self.inputs {
+            let dir = file.parent().unwrap().to_path_buf();
+            if !dirs.iter().any(|(watched, _)| *watched == dir) {
+                dirs.push((dir, RecursiveMode::NonRecursive));
+            }
+        }
+        if self.watch_static_dir {
+            dirs.push((self.static_dir.clone(), RecursiveMode::Recursive));
+        }
+        dirs
+    }
+
+    /// The watched files an event changed. Reads are ignored, since every
+    /// rebuild reads the templates.
+    fn changed_paths(&self, event: notify::Result<Event>) -> Vec<PathBuf> {
+        match event {
+            Ok(event) if !matches!(event.kind, EventKind::Access(_)) => event
+                .paths
+                .into_iter()
+                .filter(|path| self.contains(path))
+                .collect(),
+            Ok(_) => Vec::new(),
+            Err(e) => {
+                log_error("serve", &format!("failed to watch for changes: {e}"));
+                Vec::new()
+            }
+        }
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.templates.iter().any(|dir| path.starts_with(dir))
+            || self.inputs.iter().any(|file| path == file)
+            || self.watch_static_dir && path.starts_with(&self.static_dir)
+    }
+}
+
+fn serve_files(server: Server, root: &Path, version: &AtomicU64) {
+    for request in server.incoming_requests() {
+        let url = request.url().split(['?', '#']).next().unwrap_or("/");
+        let response = if url == LIVE_RELOAD_URL {
+            Response::from_string(version.load(Ordering::SeqCst).to_string())
+        } else {
+            match resolve(root, url).and_then(|path| Some((fs::read(&path).ok()?, path))) {
+                Some((bytes, path)) => {
+                    let bytes = match path.extension().and_then(|extension| extension.to_str()) {
+                        Some("html") => with_live_reload(bytes),
+                        _ => bytes,
+                    };
+                    Response::from_data(bytes).with_header(
+                        Header::from_bytes("Content-Type", content_type(&path)).unwrap(),
+                    )
+                }
+                None => Response::from_string("404 not found").with_status_code(404),
+            }
+        };
+        if let Err(e) = request.respond(response) {
+            log_error("serve", &format!("failed to respond: {e}"));
+        }
+    }
+}
+
+/// Adds the live reload script to the end of an HTML page's body.
+fn with_live_reload(html: Vec<u8>) -> Vec<u8> {
+    let html = String::from_utf8_lossy(&html);
+    match html.rfind("</body>") {
+        Some(end) => format!("{}{LIVE_RELOAD_SCRIPT}{}", &html[..end], &html[end..]),
+        None => format!("{html}{LIVE_RELOAD_SCRIPT}"),
+    }
+    .into_bytes()
+}
+
+/// The file a URL path points to, with directories meaning their index.html.
+/// Paths that would leave `root` don't point to anything.
+fn resolve(root: &Path, url: &str) -> Option<PathBuf> {
+    let url = urlencoding::decode(url).ok()?;
+    if url.split('/').any(|part| part == "..") {
+        return None;
+    }
+    let mut path = root.join(url.trim_start_matches('/'));
+    if path.is_dir() {
+        path.push("index.html");
+    }
+    path.is_file().then_some(path)
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("ics") => "text/calendar; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("ico") => "image/x-icon",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
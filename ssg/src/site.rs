@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+use std::fs;
+
+use fs_extra::{copy_items, dir};
+use icalendar::Calendar;
+use serde_json::Value;
+
+use crate::api::{data_last_updated, make_api, read_api_schema};
+use crate::calendar::{generate_calendar, make_calendar};
+use crate::config::Config;
+use crate::paths::Paths;
+use crate::utils::{log_heading, log_success, replace_placeholder_values};
+use crate::{generate_types, images, mailing_list, players, schedules};
+
+/// The files in `site/` that are written by builds (and that the next build
+/// compares against), rather than edited by hand.
+const PREVIOUS_BUILD_FILES: [&str; 3] = [
+    "api/v1/tournaments.json",
+    "api/v1/index.json",
+    "api/v1/changes.json",
+];
+
+/// Copies the hand-written parts of the site (styles, assets, the API schema)
+/// into the output directory, so a build there has everything the site needs.
+pub fn prepare_out_dir(paths: &Paths) {
+    let (site_dir, out_dir) = (&paths.static_dir, &paths.out_dir);
+    fs::create_dir_all(out_dir).unwrap();
+    if fs::canonicalize(out_dir).ok() == fs::canonicalize(site_dir).ok() {
+        return;
+    }
+    let previous_build_files = PREVIOUS_BUILD_FILES.map(|file| site_dir.join(file));
+    let mut copied = 0;
+    let mut pending = vec![site_dir.clone()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let relative = path.strip_prefix(site_dir).unwrap();
+            if path.is_dir() {
+                fs::create_dir_all(out_dir.join(relative)).unwrap();
+                pending.push(path);
+            } else if !previous_build_files.contains(&path) {
+                fs::copy(&path, out_dir.join(relative)).unwrap();
+                copied += 1;
+            }
+        }
+    }
+    log_success(
+        "site",
+        &format!("copied {copied} static files to {}", out_dir.display()),
+    );
+}
+
+/// Writes index.html, the calendar, the API, schedule and player pages for
+/// scraped tournaments, and returns the `/api/v1/tournaments.json` payload.
+pub fn render_site(
+    all_tournament_data: &[Value],
+    json_featured_players: &Value,
+    config: &Config,
+    paths: &Paths,
+) -> Value {
+    let template_header_html = paths.read_template("html/header.html");
+    let mut index_html: String = "".to_string();
+    let template_card = paths.read_template("html/templateCard.html");
+    let index_footer_html = paths.read_template("html/footer.html");
+    let mut calendar_ics = Calendar::new().name("upcoming melee majors").done();
+
+    // Only move the data timestamp forward when the scraped data actually changed,
+    // so rebuilding unchanged data produces byte-identical output
+    let last_updated = data_last_updated(all_tournament_data, paths);
+
+    log_heading("Generating site");
+    for (i, tournament_data) in all_tournament_data.iter().enumerate() {
+        if i == 0 {
+            index_html = replace_placeholder_values(tournament_data, &template_header_html);
+        }
+
+        index_html.push_str(&replace_placeholder_values(tournament_data, &template_card));
+
+        calendar_ics = generate_calendar(tournament_data.clone(), &mut calendar_ics, &last_updated);
+    }
+    index_html.push_str(&format!(
+        "\n{}",
+        index_footer_html.replace(
+            "{{email-preferences}}",
+            &mailing_list::signup_preferences_html(&config.segments)
+        )
+    ));
+    cleanup_images(&image_file_names(all_tournament_data), paths);
+    make_site(&index_html, paths);
+    log_success("html", "wrote index.html");
+    make_calendar(calendar_ics, paths);
+    log_success(
+        "calendar",
+        &format!("generated {} ICS events", all_tournament_data.len()),
+    );
+    let payload = make_api(all_tournament_data, &last_updated, paths);
+    schedules::make_schedule_pages(all_tournament_data, paths);
+    players::make_players(
+        &read_api_schema(paths),
+        all_tournament_data,
+        json_featured_players,
+        &last_updated,
+        paths,
+    );
+    generate_types::publish(paths);
+    payload
+}
+
+fn make_site(index_html: &str, paths: &Paths) {
+    fs::write(paths.site("index.html"), index_html).unwrap();
+    let site_cards_path = paths.site("assets/cards");
+    match fs::remove_dir_all(&site_cards_path) {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => panic!("failed to remove {site_cards_path}: {e}"),
+    }
+
+    copy_items(
+        &[paths.cache("cards")],
+        paths.site("assets/"),
+        &dir::CopyOptions::new().overwrite(true),
+    )
+    .unwrap();
+    // The manifest is only for the next build, not the published site
+    fs::remove_file(format!("{site_cards_path}/{}", images::MANIFEST_FILE)).ok();
+}
+
+fn image_file_names(tournaments: &[Value]) -> HashSet<String> {
+    let mut image_names = HashSet::new();
+    for tournament in tournaments {
+        if let Some(files) = tournament["image-files"].as_array() {
+            image_names.extend(
+                files
+                    .iter()
+                    .filter_map(|file| file.as_str())
+                    .map(String::from),
+            );
+        }
+    }
+
+    image_names
+}
+
+fn cleanup_images(data: &HashSet<String>, paths: &Paths) {
+    let cards_path = paths.cache("cards");
+    fs::create_dir_all(&cards_path).unwrap();
+
+    let images = fs::read_dir(cards_path).unwrap();
+    images.for_each(|image| {
+        let image_path = image.unwrap().path();
+        let image_str = image_path
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        if !data.contains(&image_str) && image_str != images::MANIFEST_FILE {
+            fs::remove_file(image_path).ok();
+        };
+    })
+}
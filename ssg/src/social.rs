@@ -0,0 +1,715 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use itertools::Itertools;
+use reqwest::multipart::{Form, Part};
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+use tokio::time::sleep;
+
+use crate::config::{BlueskyConfig, Config, MastodonConfig};
+use crate::paths::Paths;
+use crate::utils::{log_error, log_red, log_skip, log_success, log_warn};
+
+/// Bluesky counts graphemes, but characters are close enough for our posts.
+const BLUESKY_MAX_LENGTH: usize = 300;
+
+/// Bluesky rejects image blobs bigger than this.
+const BLUESKY_MAX_IMAGE_BYTES: usize = 1_000_000;
+
+/// A post announcing a tournament, before it's shaped for a network.
+pub struct SocialPost {
+    /// e.g. "Genesis X", for logging
+    pub label: String,
+    /// The tournament's slug, which names dry run files
+    pub slug: String,
+    /// Ends with `link`
+    pub text: String,
+    pub link: String,
+    /// The card image, on disk under `site/`
+    pub image_path: String,
+    pub image_alt: String,
+    /// The card image's size, for clients that lay posts out before loading it
+    pub image_width: u64,
+    pub image_height: u64,
+}
+
+#[async_trait]
+pub trait SocialNetwork: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// The request body that posts `post`, with `image` standing in for the
+    /// uploaded image (a blob or media ID, depending on the network).
+    fn payload(&self, post: &SocialPost, image: Value) -> Value;
+
+    /// Uploads the image, posts, and returns the post's URL.
+    async fn publish(&self, post: &SocialPost) -> Result<String>;
+}
+
+/// Posts tournaments that weren't in the previous build's
+/// `/api/v1/tournaments.json` to every configured network.
+///
+/// With `dry_run`, each network's payload is written to `social-previews/`
+/// instead (whether or not `social` is enabled), and without a previous build
+/// every tournament is previewed.
+pub async fn announce_new_tournaments(
+    tournaments: &[Value],
+    previous_payload: Option<&Value>,
+    config: &Config,
+    paths: &Paths,
+    dry_run: bool,
+) -> Result<()> {
+    if !config.social.enabled && !dry_run {
+        log_skip("social", "posting is turned off in config.json");
+        return Ok(());
+    }
+    let networks = networks(config);
+    if networks.is_empty() {
+        log_skip("social", "no networks configured");
+        return Ok(());
+    }
+
+    let new_tournaments = match previous_payload {
+        Some(previous_payload) => {
+            let previous_tournaments = previous_payload["tournaments"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+            tournaments
+                .iter()
+                .filter(|t| {
+                    !previous_tournaments
+                        .iter()
+                        .any(|previous| previous["slug"] == t["start.gg-tournament-name"])
+                })
+                .collect_vec()
+        }
+        None if dry_run => tournaments.iter().collect_vec(),
+        None => {
+            log_warn(
+                "social",
+                "no previous /api/v1/tournaments.json to compare against; not posting",
+            );
+            return Ok(());
+        }
+    };
+    if new_tournaments.is_empty() {
+        log_skip("social", "no new tournaments");
+        return Ok(());
+    }
+
+    let posts: Vec<SocialPost> = new_tournaments
+        .into_iter()
+        .filter_map(|tournament_data| {
+            social_post(tournament_data, paths)
+                .inspect_err(|e| {
+                    log_error("social", "failed to write post");
+                    log_red(&format!("{e:#}"));
+                })
+                .ok()
+        })
+        .collect();
+
+    if dry_run {
+        return write_previews(&networks, &posts, paths);
+    }
+    for network in &networks {
+        for post in &posts {
+            match network.publish(post).await {
+                Ok(url) => log_success(
+                    "social",
+                    &format!("{}: posted {} ({url})", network.name(), post.label),
+                ),
+                Err(e) => {
+                    log_error(
+                        "social",
+                        &format!("{}: {} failed", network.name(), post.label),
+                    );
+                    log_red(&format!("{e:#}"));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn networks(config: &Config) -> Vec<Box<dyn SocialNetwork>> {
+    let mut networks: Vec<Box<dyn SocialNetwork>> = Vec::new();
+    if let Some(bluesky) = &config.social.bluesky {
+        networks.push(Box::new(Bluesky::new(
+            bluesky,
+            secret("BLUESKY_APP_PASSWORD"),
+        )));
+    }
+    if let Some(mastodon) = &config.social.mastodon {
+        networks.push(Box::new(Mastodon::new(
+            mastodon,
+            secret("MASTODON_ACCESS_TOKEN"),
+        )));
+    }
+    networks
+}
+
+/// An env var, treating empty as unset, since unset secrets come through as
+/// empty in GitHub Actions.
+fn secret(name: &str) -> Option<String> {
+    env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// The announcement for a tournament, from its scraped data.
+fn social_post(tournament_data: &Value, paths: &Paths) -> Result<SocialPost> {
+    let name = tournament_data["name"]
+        .as_str()
+        .context("missing tournament name")?;
+    let slug = tournament_data["start.gg-tournament-name"]
+        .as_str()
+        .context("missing tournament slug")?;
+    let link = tournament_data["start.gg-url"]
+        .as_str()
+        .context("missing start.gg url")?;
+    let image_url = tournament_data["image-url"]
+        .as_str()
+        .context("missing image url")?;
+    let image_size = |key: &str| {
+        tournament_data[key]
+            .as_u64()
+            .with_context(|| format!("missing {key}"))
+    };
+
+    let featured = (0..8)
+        .filter_map(|i| tournament_data[format!("player{i}")].as_str())
+        .join(", ");
+    let mut lines = vec![
+        format!("New major: {name}"),
+        format!(
+            "{} in {}",
+            tournament_data["date"].as_str().unwrap_or_default(),
+            tournament_data["city-and-state"]
+                .as_str()
+                .unwrap_or_default()
+        ),
+    ];
+    if !featured.is_empty() {
+        lines.push(format!("Featured: {featured}"));
+    }
+    let text = |lines: &[String]| format!("{}\n\n{link}", lines.join("\n"));
+    // Drop the featured players rather than go over Bluesky's limit
+    if text(&lines).chars().count() > BLUESKY_MAX_LENGTH {
+        lines.truncate(2);
+    }
+
+    Ok(SocialPost {
+        label: name.to_string(),
+        slug: slug.to_string(),
+        text: text(&lines),
+        link: link.to_string(),
+        image_path: paths.site(image_url),
+        image_alt: format!("{name} banner"),
+        image_width: image_size("image-width")?,
+        image_height: image_size("image-height")?,
+    })
+}
+
+/// Writes each network's payload for each post to `social-previews/`.
+fn write_previews(
+    networks: &[Box<dyn SocialNetwork>],
+    posts: &[SocialPost],
+    paths: &Paths,
+) -> Result<()> {
+    let dir = paths.preview("social-previews");
+    if fs::metadata(&dir).is_ok() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+    for post in posts {
+        for network in networks {
+            let name = network.name().to_lowercase();
+            let image = json!(format!("<uploaded {}>", post.image_path));
+            fs::write(
+                format!("{dir}/{}.{name}.json", post.slug),
+                serde_json::to_string_pretty(&network.payload(post, image))?,
+            )?;
+            log_success("social", &format!("preview: {}.{name}.json", post.slug));
+        }
+    }
+    log_success(
+        "social",
+        &format!("{} previews written to {dir}", posts.len() * networks.len()),
+    );
+    Ok(())
+}
+
+/// The card image's bytes and MIME type.
+fn read_image(post: &SocialPost) -> Result<(Vec<u8>, &'static str)> {
+    let bytes = fs::read(&post.image_path)
+        .with_context(|| format!("failed to read {}", post.image_path))?;
+    let mime = match Path::new(&post.image_path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("webp") => "image/webp",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        _ => bail!("unsupported image type: {}", post.image_path),
+    };
+    Ok((bytes, mime))
+}
+
+/// Fails with the response body for context.
+async fn check_response(response: reqwest::Response) -> Result<Value> {
+    let status = response.status();
+    let body = response.json::<Value>().await.unwrap_or(Value::Null);
+    if !status.is_success() {
+        bail!("response code {}: {body}", status.as_str());
+    }
+    Ok(body)
+}
+
+/// Posts through the AT Protocol, logging in with an app password (from
+/// `BLUESKY_APP_PASSWORD` in a build).
+/// https://docs.bsky.app/docs/advanced-guides/posts
+pub struct Bluesky {
+    client: Client,
+    service: String,
+    handle: String,
+    app_password: Option<String>,
+    /// The session's access token, from logging in for the first post
+    access_token: OnceCell<String>,
+}
+
+impl Bluesky {
+    pub fn new(config: &BlueskyConfig, app_password: Option<String>) -> Self {
+        Self {
+            client: Client::new(),
+            service: config.service.trim_end_matches('/').to_string(),
+            handle: config.handle.clone(),
+            app_password,
+            access_token: OnceCell::new(),
+        }
+    }
+
+    fn xrpc(&self, method: &str) -> String {
+        format!("{}/xrpc/{method}", self.service)
+    }
+
+    /// Logs in once per run, since sessions are rate limited.
+    async fn access_token(&self) -> Result<&str> {
+        let token = self
+            .access_token
+            .get_or_try_init(|| async {
+                let password = self
+                    .app_password
+                    .as_deref()
+                    .context("BLUESKY_APP_PASSWORD isn't set")?;
+                let session = check_response(
+                    self.client
+                        .post(self.xrpc("com.atproto.server.createSession"))
+                        .json(&json!({ "identifier": self.handle, "password": password }))
+                        .send()
+                        .await?,
+                )
+                .await
+                .context("failed to log in")?;
+                session["accessJwt"]
+                    .as_str()
+                    .map(str::to_string)
+                    .context("no access token in session")
+            })
+            .await?;
+        Ok(token)
+    }
+}
+
+#[async_trait]
+impl SocialNetwork for Bluesky {
+    fn name(&self) -> &'static str {
+        "Bluesky"
+    }
+
+    fn payload(&self, post: &SocialPost, image: Value) -> Value {
+        // Links aren't detected in the text, so they need a facet, which is
+        // indexed by UTF-8 bytes
+        let link_start = post.text.rfind(&post.link).unwrap_or_default();
+        json!({
+            "repo": self.handle,
+            "collection": "app.bsky.feed.post",
+            "record": {
+                "$type": "app.bsky.feed.post",
+                "text": post.text,
+                "createdAt": Utc::now().to_rfc3339(),
+                "langs": ["en"],
+                "facets": [{
+                    "index": {
+                        "byteStart": link_start,
+                        "byteEnd": link_start + post.link.len(),
+                    },
+                    "features": [{ "$type": "app.bsky.richtext.facet#link", "uri": post.link }],
+                }],
+                "embed": {
+                    "$type": "app.bsky.embed.images",
+                    "images": [{
+                        "alt": post.image_alt,
+                        "image": image,
+                        "aspectRatio": { "width": post.image_width, "height": post.image_height },
+                    }],
+                },
+            },
+        })
+    }
+
+    async fn publish(&self, post: &SocialPost) -> Result<String> {
+        let token = self.access_token().await?;
+        let (bytes, mime) = read_image(post)?;
+        if bytes.len() > BLUESKY_MAX_IMAGE_BYTES {
+            bail!("{} is too big for Bluesky", post.image_path);
+        }
+        let upload = check_response(
+            self.client
+                .post(self.xrpc("com.atproto.repo.uploadBlob"))
+                .bearer_auth(token)
+                .header("Content-Type", mime)
+                .body(bytes)
+                .send()
+                .await?,
+        )
+        .await
+        .context("failed to upload image")?;
+
+        let created = check_response(
+            self.client
+                .post(self.xrpc("com.atproto.repo.createRecord"))
+                .bearer_auth(token)
+                .json(&self.payload(post, upload["blob"].clone()))
+                .send()
+                .await?,
+        )
+        .await?;
+        // at://<did>/app.bsky.feed.post/<record key>
+        let record_key = created["uri"]
+            .as_str()
+            .and_then(|uri| uri.rsplit('/').next())
+            .unwrap_or_default();
+        Ok(format!(
+            "https://bsky.app/profile/{}/post/{record_key}",
+            self.handle
+        ))
+    }
+}
+
+/// Posts through the Mastodon API, with an access token (with the
+/// `write:media` and `write:statuses` scopes, from `MASTODON_ACCESS_TOKEN` in
+/// a build).
+/// https://docs.joinmastodon.org/methods/statuses/#create
+pub struct Mastodon {
+    client: Client,
+    instance: String,
+    access_token: Option<String>,
+}
+
+impl Mastodon {
+    /// How many times to check on an image that's still processing.
+    const MEDIA_POLL_ATTEMPTS: u32 = 5;
+
+    pub fn new(config: &MastodonConfig, access_token: Option<String>) -> Self {
+        Self {
+            client: Client::new(),
+            instance: config.instance.trim_end_matches('/').to_string(),
+            access_token,
+        }
+    }
+}
+
+#[async_trait]
+impl SocialNetwork for Mastodon {
+    fn name(&self) -> &'static str {
+        "Mastodon"
+    }
+
+    fn payload(&self, post: &SocialPost, image: Value) -> Value {
+        json!({
+            "status": post.text,
+            "media_ids": [image],
+            "visibility": "public",
+            "language": "en",
+        })
+    }
+
+    async fn publish(&self, post: &SocialPost) -> Result<String> {
+        let token = self
+            .access_token
+            .as_deref()
+            .context("MASTODON_ACCESS_TOKEN isn't set")?;
+
+        let (bytes, mime) = read_image(post)?;
+        let file_name = Path::new(&post.image_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("card")
+            .to_string();
+        let form = Form::new()
+            .part(
+                "file",
+                Part::bytes(bytes).file_name(file_name).mime_str(mime)?,
+            )
+            .text("description", post.image_alt.clone());
+        let response = self
+            .client
+            .post(format!("{}/api/v2/media", self.instance))
+            .bearer_auth(token)
+            .multipart(form)
+            .send()
+            .await?;
+        // 202 means the image is still processing, and can't be attached yet
+        let mut processing = response.status() == StatusCode::ACCEPTED;
+        let media = check_response(response)
+            .await
+            .context("failed to upload image")?;
+        let media_id = media["id"].as_str().context("no media id")?;
+        for _ in 0..Self::MEDIA_POLL_ATTEMPTS {
+            if !processing {
+                break;
+            }
+            sleep(Duration::from_secs(1)).await;
+            let response = self
+                .client
+                .get(format!("{}/api/v1/media/{media_id}", self.instance))
+                .bearer_auth(token)
+                .send()
+                .await?;
+            processing = response.status() == StatusCode::PARTIAL_CONTENT;
+        }
+
+        // Retrying within an hour with the same key won't post twice
+        let idempotency_key = hex::encode(Sha256::digest(&post.text));
+        let status = check_response(
+            self.client
+                .post(format!("{}/api/v1/statuses", self.instance))
+                .bearer_auth(token)
+                .header("Idempotency-Key", idempotency_key)
+                .json(&self.payload(post, json!(media_id)))
+                .send()
+                .await?,
+        )
+        .await?;
+        Ok(status["url"].as_str().unwrap_or_default().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn tournament(slug: &str, name: &str, players: &[&str]) -> Value {
+        let mut tournament = json!({
+            "start.gg-tournament-name": slug,
+            "name": name,
+            "date": "October 17 - October 19",
+            "city-and-state": "San Diego, CA",
+            "start.gg-url": format!("https://www.start.gg/tournament/{slug}"),
+            "image-url": format!("/assets/cards/{slug}.webp"),
+            // A 16:9 banner at card height
+            "image-width": 302,
+            "image-height": 170,
+        });
+        for i in 0..8 {
+            tournament[format!("player{i}")] = json!(players.get(i));
+        }
+        tournament
+    }
+
+    fn config() -> Config {
+        serde_json::from_value(json!({
+            "social": {
+                "enabled": false,
+                "bluesky": { "service": "https://bsky.social/", "handle": "meleemajors.gg" },
+                "mastodon": { "instance": "https://mastodon.social" },
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn posts_drop_featured_players_over_the_length_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::for_test(dir.path());
+
+        let post = social_post(
+            &tournament("genesis", "Genesis", &["Zain", "Cody Schwab"]),
+            &paths,
+        )
+        .unwrap();
+        assert_eq!(
+            post.text,
+            "New major: Genesis\nOctober 17 - October 19 in San Diego, CA\n\
+             Featured: Zain, Cody Schwab\n\nhttps://www.start.gg/tournament/genesis"
+        );
+        assert_eq!(post.image_path, paths.site("/assets/cards/genesis.webp"));
+        assert_eq!((post.image_width, post.image_height), (302, 170));
+
+        let long_tag = "x".repeat(40);
+        let post = social_post(
+            &tournament("genesis", "Genesis", &[long_tag.as_str(); 8]),
+            &paths,
+        )
+        .unwrap();
+        assert!(!post.text.contains("Featured"));
+        assert!(post.text.chars().count() <= BLUESKY_MAX_LENGTH);
+    }
+
+    #[test]
+    fn bluesky_links_are_indexed_by_utf8_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let post = social_post(
+            &tournament("pound", "Pound Élite ✨", &[]),
+            &Paths::for_test(dir.path()),
+        )
+        .unwrap();
+        let bluesky = Bluesky::new(config().social.bluesky.as_ref().unwrap(), None);
+
+        let payload = bluesky.payload(&post, json!("<blob>"));
+        let record = &payload["record"];
+        let index = &record["facets"][0]["index"];
+        let (start, end) = (
+            index["byteStart"].as_u64().unwrap() as usize,
+            index["byteEnd"].as_u64().unwrap() as usize,
+        );
+        assert_eq!(&post.text.as_bytes()[start..end], post.link.as_bytes());
+        assert_eq!(record["facets"][0]["features"][0]["uri"], post.link);
+        assert_eq!(payload["repo"], "meleemajors.gg");
+        assert_eq!(
+            record["embed"]["images"][0],
+            json!({
+                "alt": "Pound Élite ✨ banner",
+                "image": "<blob>",
+                "aspectRatio": { "width": 302, "height": 170 },
+            })
+        );
+    }
+
+    #[test]
+    fn mastodon_payload_attaches_the_media() {
+        let dir = tempfile::tempdir().unwrap();
+        let post = social_post(
+            &tournament("genesis", "Genesis", &[]),
+            &Paths::for_test(dir.path()),
+        )
+        .unwrap();
+        let mastodon = Mastodon::new(config().social.mastodon.as_ref().unwrap(), None);
+
+        assert_eq!(
+            mastodon.payload(&post, json!("1234")),
+            json!({
+                "status": post.text,
+                "media_ids": ["1234"],
+                "visibility": "public",
+                "language": "en",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn dry_runs_preview_only_new_tournaments() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::for_test(dir.path());
+        let tournaments = [
+            tournament("genesis", "Genesis", &[]),
+            tournament("sapf", "SAPF", &[]),
+        ];
+        let previous_payload = json!({ "tournaments": [{ "slug": "genesis" }] });
+
+        announce_new_tournaments(
+            &tournaments,
+            Some(&previous_payload),
+            &config(),
+            &paths,
+            true,
+        )
+        .await
+        .unwrap();
+
+        let previews = fs::read_dir(paths.preview("social-previews"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .sorted()
+            .collect_vec();
+        assert_eq!(previews, ["sapf.bluesky.json", "sapf.mastodon.json"]);
+        let preview: Value = serde_json::from_str(
+            &fs::read_to_string(paths.preview("social-previews/sapf.mastodon.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            preview["media_ids"],
+            json!([format!(
+                "<uploaded {}>",
+                paths.site("/assets/cards/sapf.webp")
+            )])
+        );
+    }
+
+    #[tokio::test]
+    async fn bluesky_logs_in_once_per_run() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let service = format!("http://{}", server.server_addr());
+        let methods = Arc::new(Mutex::new(Vec::new()));
+        let methods_by_server = methods.clone();
+        std::thread::spawn(move || {
+            // One login, then an upload and a post for each tournament
+            for _ in 0..5 {
+                let mut request = server.recv().unwrap();
+                request.as_reader().read_to_end(&mut Vec::new()).unwrap();
+                let method = request.url().trim_start_matches("/xrpc/").to_string();
+                let body = match method.as_str() {
+                    "com.atproto.server.createSession" => json!({ "accessJwt": "token" }),
+                    "com.atproto.repo.uploadBlob" => json!({ "blob": {} }),
+                    _ => json!({ "uri": "at://did:plc:1/app.bsky.feed.post/3k" }),
+                };
+                methods_by_server.lock().unwrap().push(method);
+                request
+                    .respond(tiny_http::Response::from_string(body.to_string()))
+                    .unwrap();
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::for_test(dir.path());
+        fs::create_dir_all(paths.site("assets/cards")).unwrap();
+        let bluesky = Bluesky::new(
+            &BlueskyConfig {
+                service,
+                handle: "meleemajors.gg".to_string(),
+            },
+            Some("app-password".to_string()),
+        );
+        for slug in ["genesis", "sapf"] {
+            let post = social_post(&tournament(slug, slug, &[]), &paths).unwrap();
+            fs::write(&post.image_path, b"webp").unwrap();
+            assert_eq!(
+                bluesky.publish(&post).await.unwrap(),
+                "https://bsky.app/profile/meleemajors.gg/post/3k"
+            );
+        }
+
+        assert_eq!(
+            *methods.lock().unwrap(),
+            [
+                "com.atproto.server.createSession",
+                "com.atproto.repo.uploadBlob",
+                "com.atproto.repo.createRecord",
+                "com.atproto.repo.uploadBlob",
+                "com.atproto.repo.createRecord",
+            ]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::fs;
+use std::time::Duration;
+
+use gql_client::{Client, ClientConfig};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::time::sleep;
+
+use crate::paths::Paths;
+use crate::utils::log_skip;
+
+/// Queries start.gg, keeping every response in `startgg/` in the cache
+/// directory so the site can be re-rendered later without a token or network.
+///
+/// Responses are keyed by a name for the query and its variables, not the
+/// query text, so a regenerated getFeaturedPlayers.gql still finds the
+/// responses it replaces.
+pub struct StartggClient {
+    /// `None` when only answering from the cache
+    client: Option<Client>,
+    /// `startgg/` in the cache directory
+    dir: String,
+    used: HashSet<String>,
+}
+
+impl StartggClient {
+    pub fn new(token: &str, paths: &Paths) -> Self {
+        let mut headers = HashMap::new();
+        headers.insert("authorization".to_string(), format!("Bearer {token}"));
+        let config = ClientConfig {
+            endpoint: "https://api.start.gg/gql/alpha".to_string(),
+            timeout: Some(60),
+            headers: Some(headers),
+            proxy: None,
+        };
+        Self {
+            client: Some(Client::new_with_config(config)),
+            ..Self::cached(paths)
+        }
+    }
+
+    /// A client that only answers from responses saved by earlier builds.
+    pub fn cached(paths: &Paths) -> Self {
+        Self {
+            client: None,
+            dir: paths.cache("startgg"),
+            used: HashSet::new(),
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.client.is_none()
+    }
+
+    pub async fn query(&mut self, name: &str, query: &str, vars: Value) -> Result<Value, String> {
+        let file_name = format!("{name}.{}.json", vars_hash(&vars));
+        let path = format!("{}/{file_name}", self.dir);
+        self.used.insert(file_name);
+
+        let Some(client) = &self.client else {
+            return fs::read_to_string(&path)
+                .ok()
+                .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+                .map(|cached| cached["data"].clone())
+                .ok_or_else(|| {
+                    format!("no cached start.gg response for {name} {vars}, run `cargo run -- build` first")
+                });
+        };
+        let data = graphql_query(client, query, vars.clone()).await?;
+        fs::create_dir_all(&self.dir).map_err(|e| format!("failed to create {}: {e}", self.dir))?;
+        let cached = json!({ "vars": vars, "data": data });
+        fs::write(&path, serde_json::to_string_pretty(&cached).unwrap())
+            .map_err(|e| format!("failed to write {path}: {e}"))?;
+        Ok(data)
+    }
+
+    /// Removes responses that weren't queried by this build, e.g. for
+    /// tournaments that have been dropped from tournaments.json.
+    pub fn prune(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut removed = 0;
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if !self.used.contains(&file_name) && fs::remove_file(entry.path()).is_ok() {
+                removed += 1;
+            }
+        }
+        if removed > 0 {
+            log_skip(
+                "start.gg",
+                &format!("removed {removed} unused cached responses"),
+            );
+        }
+    }
+}
+
+fn vars_hash(vars: &Value) -> String {
+    let digest = Sha256::digest(serde_json::to_string(vars).unwrap().as_bytes());
+    hex::encode(digest)[..16].to_string()
+}
+
+async fn graphql_query(client: &Client, query: &str, vars: Value) -> Result<Value, String> {
+    const MAX_ATTEMPTS: usize = 5;
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        match client
+            .query_with_vars_unwrap::<Value, Value>(query, vars.clone())
+            .await
+        {
+            Ok(data) => {
+                return Ok(data);
+            }
+            Err(e) => {
+                let error = format_graphql_error(&e);
+                if is_unauthorized_error(&error) {
+                    return Err(format!(
+                        "start.gg GraphQL query failed with 401 Unauthorized. Check STARTGGAPI. Error details: {error}"
+                    ));
+                }
+
+                if attempt == MAX_ATTEMPTS {
+                    return Err(format!(
+                        "start.gg GraphQL query failed after {MAX_ATTEMPTS} attempts. Last error: {error}"
+                    ));
+                }
+
+                println!("Error while querying: {error}");
+                println!("Retrying in 10 seconds... ({attempt}/{MAX_ATTEMPTS})");
+                sleep(Duration::from_secs(10)).await;
+            }
+        }
+    }
+
+    unreachable!()
+}
+
+fn format_graphql_error(error: &impl Debug) -> String {
+    format!("{error:?}")
+}
+
+fn is_unauthorized_error(error: &str) -> bool {
+    error.contains("[401]") || error.contains("401") && error.contains("Unauthorized")
+}
@@ -1,13 +1,11 @@
 use anyhow::{Context, Result};
-use reqwest;
 use scraper::{Html, Selector};
-use serde_json;
 use std::collections::HashSet;
 use std::fs;
 
 use crate::utils::{log_green, log_info, log_success, log_warn};
 
-pub async fn main() -> Result<()> {
+pub async fn main(top_players_path: &str) -> Result<()> {
     log_info("rankings", "Fetching latest SSBMRank from Liquipedia...");
 
     // Fetch the SSBMRank page
@@ -43,7 +41,7 @@ pub async fn main() -> Result<()> {
     let tables: Vec<_> = document.select(&table_selector).collect();
 
     for table in tables.iter().rev() {
-        for (_row_idx, row) in table.select(&row_selector).enumerate() {
+        for row in table.select(&row_selector) {
             let cells: Vec<_> = row.select(&cell_selector).collect();
 
             // Skip empty rows
@@ -108,8 +106,6 @@ pub async fn main() -> Result<()> {
 
     log_success("rankings", &format!("Parsed {} players", top_50_players.len()));
 
-    // Use path relative to ssg/ directory (where cargo runs from)
-    let top_players_path = "src/topPlayers.json";
     let existing_players: Vec<String> =
         serde_json::from_str(&fs::read_to_string(top_players_path)?)
             .unwrap_or_else(|_| Vec::new());
@@ -140,7 +136,7 @@ pub async fn main() -> Result<()> {
         "rankings",
         &format!("Updated topPlayers.json with {} total players", final_players.len()),
     );
-    log_green("Run 'cargo run -- --generate' to update the GraphQL query");
+    log_green("Run 'cargo run -- gql generate' to update the GraphQL query");
 
     Ok(())
 }
@@ -1,38 +1,12 @@
 use serde_json::Value;
 
-/// Make all paths relative to `/ssg/src`, regardless of where `cargo run` is called from.
-pub fn absolute_path(path: &str) -> String {
-    // current_exe is in /target/debug when invoked with cargo run
-    let current_exe = std::env::current_exe().unwrap();
-
-    // from there, resolve relative path to /ssg/src
-    let absolute_path = current_exe
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("src")
-        .join(path);
-
-    absolute_path.to_str().unwrap().to_string()
-}
-
-pub fn read_file(path: &str) -> String {
-    let abs_path = absolute_path(path);
-    if let Ok(file) = std::fs::File::open(&abs_path) {
-        std::io::read_to_string(file).unwrap()
-    } else {
-        panic!("File not found: {}", &abs_path);
-    }
-}
-
 /// Replaces all instances of `{{key}}` the template string with the JSON value from `data[key]`.
+/// Arrays and objects aren't renderable, so their placeholders are left as-is.
 pub fn replace_placeholder_values(data: &Value, template: &str) -> String {
     data.as_object()
         .unwrap()
         .into_iter()
+        .filter(|(_, value)| !value.is_array() && !value.is_object())
         .fold(template.to_string(), |acc, (key, value)| {
             acc.replace(
                 &format!("{{{{{key}}}}}"), // 🤮
@@ -49,6 +23,28 @@ pub fn replace_placeholder_values(data: &Value, template: &str) -> String {
         })
 }
 
+/// Prefixes a site-relative URL like `/schedules/genesis.html` with the site's
+/// origin, for links read outside the site (emails, Discord, the API). Anything
+/// else, including empty strings, is returned as-is.
+pub fn absolute_url(url: &str) -> String {
+    if url.starts_with('/') {
+        format!("https://meleemajors.gg{url}")
+    } else {
+        url.to_string()
+    }
+}
+
+/// URL- and file-name-safe form of `text`, e.g. `Cody Schwab` -> `cody-schwab`.
+/// Non-ASCII characters are transliterated, e.g. `Ákló` -> `aklo`.
+pub fn slug(text: &str) -> String {
+    unidecode::unidecode(text)
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
 // Pretty logging
 use ansi_term::{
     Color::{Cyan, Green, Red, Yellow, RGB},
@@ -61,42 +57,42 @@ pub fn log_heading(heading: &str) {
 }
 
 pub fn log_error(label: &str, msg: &str) {
-    eprint!("{}", "❌");
+    eprint!("❌");
     if !label.is_empty() {
         eprint!(" {}", RGB(128, 128, 128).paint(format!("[{}]", label)));
     }
-    eprint!(" {}\n", msg);
+    eprintln!(" {}", msg);
 }
 
 pub fn log_warn(label: &str, msg: &str) {
-    print!("{}", "⚠️ ");
+    print!("⚠️ ");
     if !label.is_empty() {
         print!(" {}", RGB(128, 128, 128).paint(format!("[{}]", label)));
     }
-    print!(" {}\n", msg);
+    println!(" {}", msg);
 }
 
 pub fn log_skip(label: &str, msg: &str) {
-    print!("{}", "➖");
+    print!("➖");
     if !label.is_empty() {
         print!(" {}", RGB(128, 128, 128).paint(format!("[{}]", label)));
     }
-    print!(" {}\n", msg);
+    println!(" {}", msg);
 }
 
 pub fn log_success(label: &str, msg: &str) {
-    print!("{}", "✅");
+    print!("✅");
     if !label.is_empty() {
         print!(" {}", RGB(128, 128, 128).paint(format!("[{}]", label)));
     }
-    print!(" {}\n", msg);
+    println!(" {}", msg);
 }
 
 pub fn log_info(label: &str, msg: &str) {
     if !label.is_empty() {
         print!(" {}", RGB(128, 128, 128).paint(format!("[{}]", label)));
     }
-    print!(" {}\n", RGB(128, 128, 128).paint(msg));
+    println!(" {}", RGB(128, 128, 128).paint(msg));
 }
 
 pub fn log_red(msg: &str) {
@@ -114,3 +110,16 @@ pub fn log_green(msg: &str) {
 pub fn log_grey(msg: &str) {
     println!("{}", RGB(128, 128, 128).paint(msg));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugs_are_ascii() {
+        assert_eq!(slug("Cody Schwab"), "cody-schwab");
+        assert_eq!(slug("Ákló"), "aklo");
+        assert_eq!(slug("Jmook!"), "jmook");
+        assert_eq!(slug("アマテラス"), "amaterasu");
+    }
+}
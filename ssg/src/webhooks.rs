@@ -0,0 +1,404 @@
+use std::env;
+use std::fs;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::time::sleep;
+
+use crate::changes::diff_payloads;
+use crate::config::{Config, WebhookEndpoint, WebhookEvent};
+use crate::paths::Paths;
+use crate::utils::{log_error, log_red, log_skip, log_success, log_warn};
+
+/// How many times a delivery is tried, waiting twice as long after each failure.
+const MAX_ATTEMPTS: u32 = 4;
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long deliveries stay in the delivery log before they're dropped.
+const DELIVERY_LOG_RETENTION_DAYS: i64 = 30;
+
+/// An event found in this build, for one tournament.
+struct Event {
+    event: WebhookEvent,
+    slug: String,
+    /// The tournament's API object
+    tournament: Value,
+    /// `fieldChange`s, for `changed` events
+    changes: Option<Vec<Value>>,
+}
+
+/// Sends every event between the previous build's API payload and this one's
+/// to the endpoints that want it, and appends each delivery to
+/// `webhook-deliveries.jsonl` in the cache directory.
+///
+/// Bodies are described by `webhookEvent` in tournaments.schema.json, but
+/// aren't validated against it, since `removed` and `ended` events carry the
+/// previous build's tournament, which may predate a schema change.
+pub async fn send_events(
+    previous_payload: Option<&Value>,
+    current_payload: &Value,
+    config: &Config,
+    paths: &Paths,
+) {
+    if config.webhooks.is_empty() {
+        log_skip("webhooks", "no endpoints configured");
+        return;
+    }
+    let Some(previous_payload) = previous_payload else {
+        log_warn(
+            "webhooks",
+            "no previous /api/v1/tournaments.json to compare against; not sending",
+        );
+        return;
+    };
+    let events = find_events(previous_payload, current_payload);
+    if events.is_empty() {
+        log_skip("webhooks", "no events since the previous build");
+        return;
+    }
+    let timestamp = current_payload["generatedAt"].as_str().unwrap_or_default();
+
+    let client = Client::builder().timeout(REQUEST_TIMEOUT).build().unwrap();
+    let mut deliveries = Vec::new();
+    for endpoint in &config.webhooks {
+        // Unset secrets come through as empty in GitHub Actions
+        let Some(secret) = env::var(&endpoint.secret_env)
+            .ok()
+            .filter(|secret| !secret.trim().is_empty())
+        else {
+            log_warn(
+                "webhooks",
+                &format!(
+                    "{} skipped: {} isn't set",
+                    endpoint.name, endpoint.secret_env
+                ),
+            );
+            continue;
+        };
+        // Once a delivery exhausts its retries, the endpoint is likely down, so
+        // the rest are logged as skipped rather than each waiting out retries
+        let mut unreachable = false;
+        for event in events.iter().filter(|e| endpoint.events.contains(&e.event)) {
+            let id = uuid::Uuid::new_v5(
+                &uuid::Uuid::NAMESPACE_URL,
+                format!(
+                    "{}#{}:{}:{timestamp}",
+                    endpoint.url,
+                    event.event.name(),
+                    event.slug
+                )
+                .as_bytes(),
+            )
+            .to_string();
+            let mut body = json!({
+                "id": id,
+                "event": event.event.name(),
+                "timestamp": timestamp,
+                "tournament": event.tournament,
+            });
+            if let Some(changes) = &event.changes {
+                body["changes"] = json!(changes);
+            }
+
+            let label = format!("{} {}", event.event.name(), event.slug);
+            let (attempts, result) = if unreachable {
+                (
+                    0,
+                    Err(anyhow!(
+                        "skipped: an earlier delivery to {} failed",
+                        endpoint.name
+                    )),
+                )
+            } else {
+                deliver(&client, endpoint, secret.trim(), &body.to_string()).await
+            };
+            unreachable |= result.is_err() && attempts == MAX_ATTEMPTS;
+            match &result {
+                Err(_) if attempts == 0 => {
+                    log_skip("webhooks", &format!("{}: {label}", endpoint.name))
+                }
+                Ok(_) => log_success("webhooks", &format!("{}: sent {label}", endpoint.name)),
+                Err(e) => {
+                    log_error(
+                        "webhooks",
+                        &format!(
+                            "{}: {label} failed after {attempts} attempts",
+                            endpoint.name
+                        ),
+                    );
+                    log_red(&format!("{e:#}"));
+                }
+            }
+            deliveries.push(json!({
+                "id": id,
+                "endpoint": endpoint.name,
+                "event": event.event.name(),
+                "slug": event.slug,
+                "deliveredAt": Utc::now().to_rfc3339(),
+                "attempts": attempts,
+                "status": result.as_ref().ok(),
+                "error": result.as_ref().err().map(|e| format!("{e:#}")),
+            }));
+        }
+    }
+
+    let failed = deliveries
+        .iter()
+        .filter(|delivery| delivery["error"].is_string())
+        .count();
+    log_success(
+        "webhooks",
+        &format!("{} delivered, {failed} failed", deliveries.len() - failed),
+    );
+    write_delivery_log(&deliveries, paths)
+        .inspect_err(|e| {
+            log_error("webhooks", "failed to write the delivery log");
+            log_red(&format!("{e:#}"));
+        })
+        .ok();
+}
+
+/// What happened to each tournament between the two payloads. Tournaments
+/// that ended drop out of the payload, so they're `ended` rather than `removed`.
+fn find_events(previous_payload: &Value, current_payload: &Value) -> Vec<Event> {
+    let timestamp = |value: &Value| {
+        value
+            .as_str()
+            .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+            .map(|timestamp| timestamp.with_timezone(&Utc))
+    };
+    let since = timestamp(&previous_payload["generatedAt"]);
+    let now = timestamp(&current_payload["generatedAt"]).unwrap_or_else(Utc::now);
+    let slug = |tournament: &Value| tournament["slug"].as_str().unwrap_or_default().to_string();
+
+    let diff = diff_payloads(previous_payload, current_payload);
+    let mut events = Vec::new();
+    for tournament in &diff.added {
+        events.push(Event {
+            event: WebhookEvent::Added,
+            slug: slug(tournament),
+            tournament: tournament.clone(),
+            changes: None,
+        });
+    }
+    let current_tournaments = current_payload["tournaments"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    for change in &diff.changed {
+        let Some(tournament) = current_tournaments
+            .iter()
+            .find(|t| t["slug"] == change.slug)
+        else {
+            continue;
+        };
+        events.push(Event {
+            event: WebhookEvent::Changed,
+            slug: change.slug.clone(),
+            tournament: tournament.clone(),
+            changes: Some(
+                change
+                    .fields
+                    .iter()
+                    .map(
+                        |field| json!({ "field": field.field, "from": field.from, "to": field.to }),
+                    )
+                    .collect(),
+            ),
+        });
+    }
+    for tournament in &diff.removed {
+        let ended = timestamp(&tournament["endTimestamp"]).is_some_and(|end| end <= now);
+        events.push(Event {
+            event: if ended {
+                WebhookEvent::Ended
+            } else {
+                WebhookEvent::Removed
+            },
+            slug: slug(tournament),
+            tournament: tournament.clone(),
+            changes: None,
+        });
+    }
+    for tournament in &current_tournaments {
+        let started = timestamp(&tournament["startTimestamp"])
+            .is_some_and(|start| since.is_none_or(|since| since < start) && start <= now);
+        if started {
+            events.push(Event {
+                event: WebhookEvent::Started,
+                slug: slug(tournament),
+                tournament: tournament.clone(),
+                changes: None,
+            });
+        }
+    }
+    events
+}
+
+/// POSTs `body`, retrying network errors, 429s and 5xxs with exponential
+/// backoff. Returns how many attempts it took, and the final status code.
+///
+/// Requests are signed like `X-MeleeMajors-Signature: sha256=<hex HMAC-SHA256
+/// of "<X-MeleeMajors-Timestamp>.<body>" with the endpoint's secret>`, so
+/// receivers can check they came from us and reject old replays.
+async fn deliver(
+    client: &Client,
+    endpoint: &WebhookEndpoint,
+    secret: &str,
+    body: &str,
+) -> (u32, Result<u16>) {
+    let mut delay = FIRST_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let timestamp = Utc::now().timestamp().to_string();
+        let result = client
+            .post(&endpoint.url)
+            .header("Content-Type", "application/json")
+            .header("User-Agent", "meleemajors-webhooks")
+            .header("X-MeleeMajors-Timestamp", &timestamp)
+            .header(
+                "X-MeleeMajors-Signature",
+                signature(secret, &timestamp, body),
+            )
+            .body(body.to_string())
+            .send()
+            .await;
+        let (retryable, result) = match result {
+            Ok(response) if response.status().is_success() => {
+                (false, Ok(response.status().as_u16()))
+            }
+            Ok(response) => {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                let retryable = status.is_server_error() || status.as_u16() == 429;
+                (
+                    retryable,
+                    Err(anyhow!("response code {}: {text}", status.as_str())),
+                )
+            }
+            Err(e) => (true, Err(e.into())),
+        };
+        if !retryable || attempt == MAX_ATTEMPTS {
+            return (attempt, result);
+        }
+        sleep(delay).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/// The `X-MeleeMajors-Signature` header for a request.
+fn signature(secret: &str, timestamp: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Appends `deliveries` to `webhook-deliveries.jsonl`, dropping entries older
+/// than the retention window.
+fn write_delivery_log(deliveries: &[Value], paths: &Paths) -> Result<()> {
+    if deliveries.is_empty() {
+        return Ok(());
+    }
+    let path = paths.cache("webhook-deliveries.jsonl");
+    let cutoff = Utc::now() - chrono::Duration::days(DELIVERY_LOG_RETENTION_DAYS);
+    let mut lines: Vec<String> = fs::read_to_string(&path)
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| {
+            serde_json::from_str::<Value>(line)
+                .ok()
+                .and_then(|delivery| {
+                    DateTime::parse_from_rfc3339(delivery["deliveredAt"].as_str()?).ok()
+                })
+                .is_some_and(|delivered_at| delivered_at > cutoff)
+        })
+        .map(String::from)
+        .collect();
+    for delivery in deliveries {
+        lines.push(serde_json::to_string(delivery)?);
+    }
+    fs::write(&path, lines.join("\n") + "\n")?;
+    log_success("webhooks", &format!("logged to {path}"));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tournament(slug: &str, start: &str, end: &str, entrants: u64) -> Value {
+        json!({
+            "slug": slug,
+            "name": slug,
+            "startTimestamp": start,
+            "endTimestamp": end,
+            "entrants": entrants,
+        })
+    }
+
+    #[test]
+    fn events_since_the_previous_build() {
+        let previous = json!({
+            "generatedAt": "2026-10-18T10:00:00Z",
+            "tournaments": [
+                tournament("genesis", "2026-10-17T09:00:00Z", "2026-10-19T23:00:00Z", 1000),
+                tournament("ended", "2026-10-16T09:00:00Z", "2026-10-18T11:00:00Z", 300),
+                tournament("cancelled", "2026-11-01T09:00:00Z", "2026-11-03T23:00:00Z", 200),
+                tournament("starting", "2026-10-18T11:00:00Z", "2026-10-20T23:00:00Z", 400),
+            ],
+        });
+        let current = json!({
+            "generatedAt": "2026-10-18T12:00:00Z",
+            "tournaments": [
+                tournament("genesis", "2026-10-17T09:00:00Z", "2026-10-19T23:00:00Z", 1100),
+                tournament("starting", "2026-10-18T11:00:00Z", "2026-10-20T23:00:00Z", 400),
+                tournament("new", "2026-12-01T09:00:00Z", "2026-12-03T23:00:00Z", 100),
+            ],
+        });
+
+        let events = find_events(&previous, &current);
+        let found: Vec<(&str, &str)> = events
+            .iter()
+            .map(|e| (e.event.name(), e.slug.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("added", "new"),
+                ("changed", "genesis"),
+                ("ended", "ended"),
+                ("removed", "cancelled"),
+                ("started", "starting"),
+            ]
+        );
+        assert_eq!(
+            events[1].changes,
+            Some(vec![
+                json!({ "field": "entrants", "from": 1000, "to": 1100 })
+            ])
+        );
+        assert_eq!(events[1].tournament["entrants"], 1100);
+        // Removed tournaments are as of the previous build
+        assert_eq!(events[3].tournament["entrants"], 200);
+
+        // Nothing happens between identical builds
+        assert!(find_events(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn signature_is_an_hmac_of_the_timestamp_and_body() {
+        // printf '%s' '1760900000.{"id":"1"}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            signature("secret", "1760900000", r#"{"id":"1"}"#),
+            "sha256=4d15689f20912a958515b58788296d859915e2acb275b55b5444661cf68074ea"
+        );
+    }
+}